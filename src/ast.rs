@@ -1,8 +1,13 @@
+use serde::Serialize;
+use std::fmt;
+
 /// Abstract Syntax Tree for Charta programs
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Module {
     pub name: String,
+    /// Declared as `library module`: no runnable entry is expected
+    pub is_library: bool,
     pub context: Option<String>,
     pub intent: Option<Intent>,
     pub constraints: Option<Constraints>,
@@ -13,63 +18,81 @@ pub struct Module {
     pub networks: Vec<NetworkDecl>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Intent {
     pub goal: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Constraints {
     pub data_privacy: Option<DataPrivacy>,
     pub quality: Option<Quality>,
     pub cost: Option<Cost>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DataPrivacy {
     pub jurisdiction: Option<String>,
     pub pii_handling: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Quality {
     pub min_precision: Option<f64>,
     pub min_recall: Option<f64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Cost {
     pub max_cost_per_submission: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SignalDecl {
     pub name: String,
     pub parameters: Vec<String>,
     pub type_: Option<String>,
+    /// Engineering unit, e.g. `unit: "degC"`
+    pub unit: Option<String>,
+    /// Feature named by a `#[if feature("...")]` gate
+    pub feature: Option<String>,
+    /// Message of a `@deprecated("...")` attribute; empty when none was given
+    pub deprecated: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CoilDecl {
     pub name: String,
     pub parameters: Vec<String>,
     pub latching: Option<bool>,
     pub critical: Option<bool>,
+    /// Engineering unit, e.g. `unit: "bar"`
+    pub unit: Option<String>,
+    /// Feature named by a `#[if feature("...")]` gate
+    pub feature: Option<String>,
+    /// Message of a `@deprecated("...")` attribute; empty when none was given
+    pub deprecated: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RungDecl {
     pub name: String,
     pub guard: GuardExpr,
     pub actions: Vec<Action>,
+    /// Actions performed when the guard does not hold
+    pub else_actions: Vec<Action>,
+    /// Feature named by a `#[if feature("...")]` gate
+    pub feature: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum GuardExpr {
     Contact {
         name: String,
         contact_type: ContactType,
         arguments: Vec<Expr>,
+        /// What the contact reads, filled in by name resolution
+        kind: Option<ContactKind>,
     },
     And {
         left: Box<GuardExpr>,
@@ -82,15 +105,115 @@ pub enum GuardExpr {
     Not {
         expr: Box<GuardExpr>,
     },
+    /// `left op right`, e.g. `temperature > 80`; `left` is always an identifier
+    Compare {
+        left: Expr,
+        op: CompareOp,
+        right: Expr,
+    },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CompareOp {
+    Gt,
+    Lt,
+    Ge,
+    Le,
+    Eq,
+    Ne,
+}
+
+impl CompareOp {
+    /// Source and IR spelling
+    pub fn symbol(self) -> &'static str {
+        match self {
+            CompareOp::Gt => ">",
+            CompareOp::Lt => "<",
+            CompareOp::Ge => ">=",
+            CompareOp::Le => "<=",
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+        }
+    }
+    
+    /// The operator holding exactly when this one does not
+    pub fn negated(self) -> Self {
+        match self {
+            CompareOp::Gt => CompareOp::Le,
+            CompareOp::Lt => CompareOp::Ge,
+            CompareOp::Ge => CompareOp::Lt,
+            CompareOp::Le => CompareOp::Gt,
+            CompareOp::Eq => CompareOp::Ne,
+            CompareOp::Ne => CompareOp::Eq,
+        }
+    }
+}
+
+/// Renders guards in source syntax, parenthesizing only where precedence requires
+impl fmt::Display for GuardExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuardExpr::Contact { name, contact_type, arguments, .. } => {
+                write!(f, "{} {}", contact_type.symbol(), name)?;
+                if !arguments.is_empty() {
+                    let args: Vec<String> = arguments.iter().map(|a| a.to_string()).collect();
+                    write!(f, "({})", args.join(", "))?;
+                }
+                Ok(())
+            }
+            GuardExpr::And { left, right } => {
+                for (i, side) in [left, right].into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " AND ")?;
+                    }
+                    match **side {
+                        GuardExpr::Or { .. } => write!(f, "({})", side)?,
+                        _ => write!(f, "{}", side)?,
+                    }
+                }
+                Ok(())
+            }
+            GuardExpr::Or { left, right } => write!(f, "{} OR {}", left, right),
+            GuardExpr::Not { expr } => match **expr {
+                GuardExpr::Contact { .. } | GuardExpr::Compare { .. } => write!(f, "NOT {}", expr),
+                _ => write!(f, "NOT ({})", expr),
+            },
+            GuardExpr::Compare { left, op, right } => write!(f, "{} {} {}", left, op.symbol(), right),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum ContactType {
     NO, // Normally Open
     NC, // Normally Closed
+    RisingEdge,  // P: passes for the one scan its input goes high
+    FallingEdge, // N: passes for the one scan its input goes low
+}
+
+impl ContactType {
+    /// Source and IR spelling
+    pub fn symbol(self) -> &'static str {
+        match self {
+            ContactType::NO => "NO",
+            ContactType::NC => "NC",
+            ContactType::RisingEdge => "P",
+            ContactType::FallingEdge => "N",
+        }
+    }
+    
+    pub fn is_edge(self) -> bool {
+        matches!(self, ContactType::RisingEdge | ContactType::FallingEdge)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ContactKind {
+    Signal,
+    Coil,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Expr {
     String(String),
     Number(f64),
@@ -98,58 +221,129 @@ pub enum Expr {
     Identifier(String),
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::String(s) => write!(f, "{:?}", s),
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::Boolean(b) => write!(f, "{}", b),
+            Expr::Identifier(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Action {
     pub action_type: ActionType,
     pub coil: String,
     pub arguments: Vec<Expr>,
+    /// Explicit output for `energise c = expr`; `None` energises to true
+    pub value: Option<Expr>,
+    /// Asserted condition of `require <guard>`; such actions have an empty coil
+    pub condition: Option<GuardExpr>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum ActionType {
     Energise,
     DeEnergise,
     Escalate,
     Require,
+    /// Turn a latching coil on until it is reset
+    Set,
+    /// Turn a latching coil off
+    Reset,
+}
+
+impl ActionType {
+    /// Name used for this action in IR
+    pub fn ir_name(&self) -> &'static str {
+        match self {
+            ActionType::Energise => "energise",
+            ActionType::DeEnergise => "de_energise",
+            ActionType::Escalate => "escalate",
+            ActionType::Require => "require",
+            ActionType::Set => "set",
+            ActionType::Reset => "reset",
+        }
+    }
+    
+    pub fn from_ir_name(name: &str) -> Option<Self> {
+        match name {
+            "energise" => Some(ActionType::Energise),
+            "de_energise" => Some(ActionType::DeEnergise),
+            "escalate" => Some(ActionType::Escalate),
+            "require" => Some(ActionType::Require),
+            "set" => Some(ActionType::Set),
+            "reset" => Some(ActionType::Reset),
+            _ => None,
+        }
+    }
+    
+    /// Whether the action reaches beyond the module's own coils
+    pub fn has_side_effects(&self) -> bool {
+        matches!(self, ActionType::Escalate)
+    }
+}
+
+/// 1-based source position of a declaration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct BlockDecl {
     pub name: String,
+    /// Where the declaration starts; `None` for synthesized blocks
+    pub position: Option<Position>,
+    /// Declared `abstract block`: an interface-only placeholder
+    pub is_abstract: bool,
     pub inputs: Vec<PortDecl>,
     pub outputs: Vec<PortDecl>,
     pub internals: Vec<InternalDecl>,
     pub implementation: Option<String>,
     pub effect: Option<String>,
+    /// Feature named by a `#[if feature("...")]` gate
+    pub feature: Option<String>,
+    /// Message of a `@deprecated("...")` attribute; empty when none was given
+    pub deprecated: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PortDecl {
     pub name: String,
     pub type_: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct InternalDecl {
     pub name: String,
     pub type_: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct NetworkDecl {
     pub name: String,
     pub wires: Vec<Wire>,
     pub outputs: Vec<Output>,
+    /// Feature named by a `#[if feature("...")]` gate
+    pub feature: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Wire {
     pub source: String,
     pub target: String,
+    /// Where the wire is declared; `None` for synthesized wires
+    pub position: Option<Position>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Output {
     pub name: String,
     pub source: String,
+    /// Where the output is declared; `None` for synthesized outputs
+    pub position: Option<Position>,
 }
@@ -1,12 +1,19 @@
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+use crate::codegen::generate_inputs_struct;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::emitter::{decode_ir_binary, BINARY_IR_MAGIC};
 use crate::error::{Result, CompileError};
-use crate::{parse, resolve_names, emit_ir};
+use crate::parser::{lex, Token};
+use crate::{parse, parse_all, parse_with_warnings, resolve_names, resolve_names_with, check_types, emit_ir, emitter_for, validate_ir_semantics, EmitOptions, ResolveOptions};
+use crate::validation::ir_coil_dependencies;
 use charta_core::ir::validation::validate_ir;
 use charta_vm::VM;
 use charta_vm::ir::load_ir;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 #[command(name = "charta")]
@@ -20,21 +27,96 @@ pub struct Cli {
 pub enum Commands {
     /// Compile Charta source to IR
     Compile {
-        /// Input Charta source file
+        /// Input Charta source file, or a directory to compile every `.charta` file in
         #[arg(short, long)]
         input: PathBuf,
-        /// Output IR file
+        /// Output IR file (`-` for stdout)
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// Suppress progress and status messages
+        #[arg(short, long)]
+        quiet: bool,
+        /// Include a coil-to-signal dependency map in IR metadata
+        #[arg(long)]
+        with_deps: bool,
+        /// Output format (json, yaml, bincode, or a registered custom format)
+        #[arg(long, default_value = "json")]
+        format: String,
+        /// Stop reporting parse errors after this many
+        #[arg(long, default_value_t = 20)]
+        max_errors: usize,
+        /// Warn about signals read as both NO and NC
+        #[arg(long)]
+        warn_mixed_contacts: bool,
+        /// Treat every lint warning as an error
+        #[arg(long)]
+        strict: bool,
+        /// Enable a feature, keeping declarations gated with `#[if feature("...")]`
+        #[arg(long = "feature")]
+        features: Vec<String>,
+        /// Silence the note on NC contacts that can never pass for this coil
+        #[arg(long)]
+        allow_nc: Vec<String>,
+        /// How to print diagnostics
+        #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+        /// Write outputs under this directory, mirroring the input layout
+        #[arg(long, conflicts_with = "output")]
+        out_dir: Option<PathBuf>,
+        /// Compile and report the output paths without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Lower `else` branches and explicit values to primitive rungs and actions
+        #[arg(long)]
+        lower: bool,
+        /// Add a readable `guard_text` to each rung alongside the structured guard
+        #[arg(long)]
+        with_guard_text: bool,
+        /// Sort the operands of `AND`/`OR` chains so reordered guards emit identical IR
+        #[arg(long)]
+        canonical_guards: bool,
+        /// Write JSON IR on a single line instead of indented
+        #[arg(long)]
+        compact: bool,
     },
     /// Run IR program on VM
     Run {
-        /// Input IR file
+        /// Input IR file (`-` for stdin)
         #[arg(short, long)]
         input: PathBuf,
+        /// Treat the input as Charta source and compile it in memory first
+        #[arg(long)]
+        from_source: bool,
         /// Input values as JSON (optional)
         #[arg(long)]
         inputs: Option<String>,
+        /// JSON file with one input object, or an array of them replayed one per scan
+        #[arg(long, conflicts_with = "inputs")]
+        inputs_file: Option<PathBuf>,
+        /// Initial coil values as JSON, supplied with the first scan's inputs
+        #[arg(long)]
+        initial: Option<String>,
+    },
+    /// Compile source in memory and run one cycle on the VM
+    Exec {
+        /// Input Charta source file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Input values as JSON (optional)
+        #[arg(long)]
+        inputs: Option<String>,
+    },
+    /// Print the parsed syntax tree of a Charta source file
+    Ast {
+        /// Input Charta source file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Print the tree as JSON
+        #[arg(long)]
+        json: bool,
+        /// Resolve names first, filling in contact kinds
+        #[arg(long)]
+        resolve: bool,
     },
     /// Validate Charta source file
     Validate {
@@ -42,93 +124,858 @@ pub enum Commands {
         #[arg(short, long)]
         input: PathBuf,
     },
+    /// Format a Charta source file canonically
+    Fmt {
+        /// Input Charta source file, rewritten in place
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Fail if the file is not formatted instead of rewriting it
+        #[arg(long)]
+        check: bool,
+    },
     /// Inspect IR file
     Inspect {
         /// Input IR file
         #[arg(short, long)]
         input: PathBuf,
+        /// Order of entries within each group
+        #[arg(long, value_enum, default_value_t = InspectOrder::Source)]
+        order: InspectOrder,
+    },
+    /// Print coil states for every combination of signal inputs (single scan)
+    TruthTable {
+        /// Input IR file
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+    /// Check two modules drive identical coils for every input combination
+    Equiv {
+        /// First Charta source file
+        #[arg(long)]
+        a: PathBuf,
+        /// Second Charta source file
+        #[arg(long)]
+        b: PathBuf,
+    },
+    /// Check that no two of the named coils are energised in the same scan,
+    /// for every combination of signal inputs
+    InterlockCheck {
+        /// Input IR file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Comma-separated coils that must be mutually exclusive
+        #[arg(long, value_delimiter = ',', required = true)]
+        exclusive: Vec<String>,
+    },
+    /// Print every token with its line and column, for debugging positions
+    #[command(hide = true)]
+    Tokens {
+        /// Input Charta source file
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+    /// Generate a typed Rust inputs struct for a module's signals
+    CodegenInputs {
+        /// Input Charta source file
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+    /// Benchmark compiler throughput
+    Bench {
+        /// Input Charta source file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Number of times to compile the file
+        #[arg(short = 'n', long, default_value_t = 10)]
+        iterations: usize,
+        /// Print timings as JSON
+        #[arg(long)]
+        json: bool,
     },
 }
 
+/// Rendering of compiler diagnostics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum MessageFormat {
+    /// Plain text for terminals
+    #[default]
+    Human,
+    /// GitHub Actions workflow commands, shown as inline annotations
+    Github,
+}
+
+/// Ordering of declarations listed by `inspect`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InspectOrder {
+    /// Declaration order in the source, as preserved by the emitter
+    Source,
+    /// Alphabetical by name
+    Alpha,
+}
+
 pub fn run_cli() -> Result<()> {
     let cli = Cli::parse();
     
     match cli.command {
-        Commands::Compile { input, output } => {
-            compile_command(&input, output.as_ref())?;
+        Commands::Compile { input, output, quiet, with_deps, format, max_errors, warn_mixed_contacts, strict, features, allow_nc, message_format, out_dir, dry_run, lower, with_guard_text, canonical_guards, compact } => {
+            let options = CompileOptions {
+                quiet,
+                dry_run,
+                out_dir,
+                format,
+                max_errors,
+                message_format,
+                resolve: ResolveOptions { strict, mixed_contacts: warn_mixed_contacts, features, allow_nc },
+                emit: EmitOptions { with_deps, lower, guard_text: with_guard_text, canonical_guards, pretty: !compact },
+            };
+            compile_command(&input, output.as_ref(), &options)?;
         }
-        Commands::Run { input, inputs } => {
-            run_command(&input, inputs.as_deref())?;
+        Commands::Run { input, from_source, inputs, inputs_file, initial } => {
+            let ir_content = read_run_input(&input, from_source, &mut io::stdin().lock())?;
+            let vectors = match inputs_file {
+                Some(path) => {
+                    let vectors = input_vectors(&fs::read_to_string(&path)?)?;
+                    check_input_vectors(&ir_content, &vectors)?;
+                    vectors
+                }
+                None => match inputs {
+                    Some(inputs) => input_vectors(&inputs)?,
+                    None => vec![HashMap::new()],
+                },
+            };
+            let (vectors, warnings) = typed_input_vectors(&ir_content, vectors)?;
+            for warning in &warnings {
+                eprintln!("{}", warning);
+            }
+            print!("{}", run_report(&ir_content, vectors, initial.as_deref())?);
+        }
+        Commands::Exec { input, inputs } => {
+            let source = fs::read_to_string(&input)
+                .map_err(CompileError::Io)?;
+            print!("{}", exec_report(&source, inputs.as_deref())?);
+        }
+        Commands::Ast { input, json, resolve } => {
+            let source = fs::read_to_string(&input)
+                .map_err(CompileError::Io)?;
+            println!("{}", ast_report(&source, json, resolve)?);
         }
         Commands::Validate { input } => {
             validate_command(&input)?;
         }
-        Commands::Inspect { input } => {
-            inspect_command(&input)?;
+        Commands::Fmt { input, check } => {
+            fmt_command(&input, check)?;
+        }
+        Commands::Inspect { input, order } => {
+            inspect_command(&input, order)?;
+        }
+        Commands::TruthTable { input } => {
+            let ir_content = read_ir(&input)?;
+            print!("{}", truth_table_report(&ir_content)?);
+        }
+        Commands::Equiv { a, b } => {
+            let source_a = fs::read_to_string(&a)
+                .map_err(CompileError::Io)?;
+            let source_b = fs::read_to_string(&b)
+                .map_err(CompileError::Io)?;
+            match equiv_check(&source_a, &source_b)? {
+                None => println!("{} and {} are equivalent", a.display(), b.display()),
+                Some(difference) => {
+                    println!("Not equivalent: {}", difference);
+                    return Err(CompileError::Aborted(1));
+                }
+            }
+        }
+        Commands::InterlockCheck { input, exclusive } => {
+            let ir_content = read_ir(&input)?;
+            match interlock_check(&ir_content, &exclusive)? {
+                None => println!("{} are mutually exclusive", exclusive.join(", ")),
+                Some(counterexample) => {
+                    println!("Interlock violated: {}", counterexample);
+                    return Err(CompileError::Aborted(1));
+                }
+            }
+        }
+        Commands::Tokens { input } => {
+            let source = fs::read_to_string(&input)
+                .map_err(CompileError::Io)?;
+            print!("{}", tokens_report(&source));
+        }
+        Commands::CodegenInputs { input } => {
+            let source = fs::read_to_string(&input)
+                .map_err(CompileError::Io)?;
+            let mut module = parse(&source)?;
+            resolve_names(&mut module)?;
+            print!("{}", generate_inputs_struct(&module));
+        }
+        Commands::Bench { input, iterations, json } => {
+            bench_command(&input, iterations, json)?;
+        }
+    }
+    
+    Ok(())
+}
+
+/// Options shared by single-file and batch compilation
+struct CompileOptions {
+    quiet: bool,
+    dry_run: bool,
+    out_dir: Option<PathBuf>,
+    format: String,
+    max_errors: usize,
+    message_format: MessageFormat,
+    resolve: ResolveOptions,
+    emit: EmitOptions,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            quiet: false,
+            dry_run: false,
+            out_dir: None,
+            format: "json".to_string(),
+            max_errors: 20,
+            message_format: MessageFormat::Human,
+            resolve: ResolveOptions::default(),
+            emit: EmitOptions::default(),
+        }
+    }
+}
+
+fn compile_command(input: &PathBuf, output: Option<&PathBuf>, options: &CompileOptions) -> Result<()> {
+    if input.is_dir() {
+        if output.is_some() {
+            return Err(CompileError::emission(
+                "--output cannot be used when compiling a directory".to_string()
+            ));
+        }
+        let tty = io::stderr().is_terminal();
+        return compile_dir(input, options, tty, &mut io::stderr());
+    }
+    let placed = match (&options.out_dir, input.file_name()) {
+        (Some(out_dir), Some(name)) => Some(out_dir_path(out_dir, Path::new(name), options)?),
+        _ => None,
+    };
+    compile_to(input, placed.as_ref().or(output), options, &mut io::stdout(), &mut io::stderr())
+}
+
+/// Output path for `relative` under `out_dir`, creating its parent directories
+/// unless this is a dry run
+fn out_dir_path(out_dir: &Path, relative: &Path, options: &CompileOptions) -> Result<PathBuf> {
+    let extension = emitter_for(&options.format, options.emit.clone())?.extension().to_string();
+    let path = out_dir.join(relative).with_extension(extension);
+    if let Some(parent) = path.parent().filter(|_| !options.dry_run) {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(path)
+}
+
+/// Collect `.charta` files under `dir` recursively, sorted for a stable order
+fn collect_sources(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut sources = Vec::new();
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            sources.extend(collect_sources(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "charta") {
+            sources.push(path);
+        }
+    }
+    Ok(sources)
+}
+
+/// Compile every source under `dir`, continuing past failures.
+/// Progress lines are written only when `tty` is set and not in quiet mode.
+fn compile_dir(dir: &Path, options: &CompileOptions, tty: bool, err: &mut dyn Write) -> Result<()> {
+    let sources = collect_sources(dir)?;
+    let total = sources.len();
+    check_module_names_unique(&sources, err)?;
+    let show_progress = tty && !options.quiet;
+    let mut failures = 0;
+    
+    for (index, source) in sources.iter().enumerate() {
+        if show_progress {
+            writeln!(err, "[{}/{}] {}", index + 1, total, source.display())?;
+        }
+        let placed = match &options.out_dir {
+            Some(out_dir) => {
+                let relative = source.strip_prefix(dir).unwrap_or(source);
+                Some(out_dir_path(out_dir, relative, options)?)
+            }
+            None => None,
+        };
+        if let Err(e) = compile_to(source, placed.as_ref(), options, &mut io::sink(), err) {
+            failures += 1;
+            writeln!(err, "error: {}: {}", source.display(), e)?;
         }
     }
     
+    if failures > 0 {
+        return Err(CompileError::emission(
+            format!("{} of {} files failed to compile", failures, total)
+        ));
+    }
     Ok(())
 }
 
-fn compile_command(input: &PathBuf, output: Option<&PathBuf>) -> Result<()> {
+/// Name declared by the `module` header of `source`, if it has one
+fn declared_module_name(source: &str) -> Option<String> {
+    let tokens = lex(source);
+    let header = tokens.iter().position(|(token, _)| *token == Token::Module)?;
+    match tokens.get(header + 1) {
+        Some((Token::Identifier(name), _)) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// Fail when two sources in a batch declare the same module name.
+/// Unreadable or headerless sources are left for compilation to report.
+fn check_module_names_unique(sources: &[PathBuf], err: &mut dyn Write) -> Result<()> {
+    let mut declared: HashMap<String, &PathBuf> = HashMap::new();
+    let mut collisions = 0;
+    for source in sources {
+        let Some(name) = fs::read_to_string(source).ok().and_then(|s| declared_module_name(&s)) else {
+            continue;
+        };
+        if let Some(first) = declared.get(&name) {
+            collisions += 1;
+            writeln!(err, "error: module '{}' is declared in both {} and {}", name, first.display(), source.display())?;
+        } else {
+            declared.insert(name, source);
+        }
+    }
+    
+    if collisions > 0 {
+        return Err(CompileError::emission(
+            format!("{} duplicate module name(s) in batch", collisions)
+        ));
+    }
+    Ok(())
+}
+
+/// Render a diagnostic for `file` in the requested format
+fn format_diagnostic(diagnostic: &Diagnostic, file: &Path, format: MessageFormat) -> String {
+    match format {
+        MessageFormat::Human => diagnostic.to_string(),
+        MessageFormat::Github => {
+            let command = match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Note => "notice",
+            };
+            let mut properties = format!("file={}", file.display());
+            if let (Some(line), Some(column)) = (diagnostic.line, diagnostic.column) {
+                properties.push_str(&format!(",line={},col={}", line, column));
+            }
+            // Workflow commands end at a newline, so multi-line messages must be escaped
+            let message = diagnostic.message.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A");
+            format!("::{} {}::{}", command, properties, message)
+        }
+    }
+}
+
+/// Compile `input`, writing IR payload to `out` (for `--output -`) and
+/// diagnostics and status messages to `err`
+fn compile_to(input: &PathBuf, output: Option<&PathBuf>, options: &CompileOptions, out: &mut dyn Write, err: &mut dyn Write) -> Result<()> {
     let source = fs::read_to_string(input)
         .map_err(CompileError::Io)?;
     
-    // Parse
-    let mut module = parse(&source)?;
+    // Parse, reporting every recovered error before giving up; `--strict` makes parser lints errors too
+    let (module, mut diagnostics) = parse_all(&source, options.max_errors);
+    for diagnostic in &mut diagnostics {
+        if options.resolve.strict && diagnostic.severity == Severity::Warning {
+            diagnostic.severity = Severity::Error;
+        }
+        writeln!(err, "{}", format_diagnostic(diagnostic, input, options.message_format))?;
+    }
+    let error_count = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    let mut module = match module {
+        Some(module) if error_count == 0 => module,
+        _ => return Err(CompileError::Aborted(error_count)),
+    };
     
     // Resolve names
-    resolve_names(&mut module)?;
+    let warnings = match resolve_names_with(&mut module, &options.resolve) {
+        Ok(warnings) => warnings,
+        Err(e) => {
+            writeln!(err, "{}", format_diagnostic(&Diagnostic::from(&e), input, options.message_format))?;
+            return Err(CompileError::Aborted(1));
+        }
+    };
+    for warning in &warnings {
+        writeln!(err, "{}", format_diagnostic(warning, input, options.message_format))?;
+    }
+    
+    // Check types
+    if let Err(e) = check_types(&module) {
+        writeln!(err, "{}", format_diagnostic(&Diagnostic::from(&e), input, options.message_format))?;
+        return Err(CompileError::Aborted(1));
+    }
     
     // Emit IR
-    let ir_json = emit_ir(&module)?;
+    let emitter = emitter_for(&options.format, options.emit.clone())?;
+    if output.is_some_and(|p| p.as_os_str() == "-") {
+        emitter.emit_to(&module, out)?;
+        if !emitter.is_binary() {
+            writeln!(out)?;
+        }
+        return Ok(());
+    }
     
-    // Write output
-    let output_path = output.map(|p| p.clone())
+    let output_path = output.cloned()
         .unwrap_or_else(|| {
-            input.with_extension("ir.json")
+            input.with_extension(emitter.extension())
         });
     
-    fs::write(&output_path, ir_json)
-        .map_err(CompileError::Io)?;
+    if options.dry_run {
+        let ir_bytes = emitter.emit_bytes(&module)?;
+        writeln!(err, "Would write {} ({} bytes)", output_path.display(), ir_bytes.len())?;
+        return Ok(());
+    }
+    
+    // Write beside the output and rename over it, so a failed emit keeps the previous output
+    let mut partial = output_path.clone().into_os_string();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let written = fs::File::create(&partial).map_err(CompileError::Io).and_then(|file| {
+        let mut file = io::BufWriter::new(file);
+        emitter.emit_to(&module, &mut file)?;
+        file.flush().map_err(CompileError::Io)
+    });
+    if let Err(e) = written.and_then(|()| fs::rename(&partial, &output_path).map_err(CompileError::Io)) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
     
-    println!("Compiled {} to {}", input.display(), output_path.display());
+    if !options.quiet {
+        writeln!(err, "Compiled {} to {}", input.display(), output_path.display())?;
+    }
     Ok(())
 }
 
-fn run_command(input: &PathBuf, inputs_json: Option<&str>) -> Result<()> {
-    let ir_content = fs::read_to_string(input)
-        .map_err(CompileError::Io)?;
+/// Read an IR file as JSON text, decoding binary IR by its header
+fn read_ir(path: &Path) -> Result<String> {
+    ir_text(fs::read(path)?)
+}
+
+fn ir_text(bytes: Vec<u8>) -> Result<String> {
+    if bytes.starts_with(BINARY_IR_MAGIC) {
+        return decode_ir_binary(&bytes);
+    }
+    String::from_utf8(bytes)
+        .map_err(|e| CompileError::emission(format!("IR file is not UTF-8: {}", e)))
+}
+
+/// IR JSON for `run`, read from `input` or from `stdin` when it is `-`.
+/// With `from_source` the input is Charta source, compiled in memory.
+fn read_run_input(input: &Path, from_source: bool, stdin: &mut dyn Read) -> Result<String> {
+    let bytes = if input.as_os_str() == "-" {
+        let mut bytes = Vec::new();
+        stdin.read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        fs::read(input)?
+    };
+    if !from_source {
+        return ir_text(bytes);
+    }
+    let source = String::from_utf8(bytes)
+        .map_err(|e| CompileError::emission(format!("Source is not UTF-8: {}", e)))?;
+    compile_in_memory(&source).map(|(_, ir)| ir)
+}
+
+/// Parse, resolve and type-check `source`, emitting lowered IR the VM can run; warnings go to stderr
+fn compile_in_memory(source: &str) -> Result<(crate::ast::Module, String)> {
+    let mut module = parse(source)?;
+    for warning in resolve_names(&mut module)? {
+        eprintln!("{}", warning);
+    }
+    check_types(&module)?;
+    let ir = emit_runnable_ir(&module)?;
+    Ok((module, ir))
+}
+
+/// Render the syntax tree of `source` as JSON or as Rust debug output
+fn ast_report(source: &str, json: bool, resolve: bool) -> Result<String> {
+    let mut module = parse(source)?;
+    if resolve {
+        for warning in resolve_names(&mut module)? {
+            eprintln!("{}", warning);
+        }
+    }
+    if !json {
+        return Ok(format!("{:#?}", module));
+    }
+    serde_json::to_string_pretty(&module)
+        .map_err(|e| CompileError::emission(format!("JSON serialization error: {}", e)))
+}
+
+/// One `line:column token` row per lexed token
+fn tokens_report(source: &str) -> String {
+    lex(source).into_iter()
+        .map(|(token, span)| format!("{}:{} {:?}\n", span.line, span.column, token))
+        .collect()
+}
+
+/// Parse a JSON input object, or an array of them, into per-scan input vectors
+fn input_vectors(json: &str) -> Result<Vec<HashMap<String, serde_json::Value>>> {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Vectors {
+        One(HashMap<String, serde_json::Value>),
+        Many(Vec<HashMap<String, serde_json::Value>>),
+    }
+    let vectors = serde_json::from_str(json)
+        .map_err(|e| CompileError::emission(format!("Invalid inputs JSON: {}", e)))?;
+    Ok(match vectors {
+        Vectors::One(vector) => vec![vector],
+        Vectors::Many(vectors) => vectors,
+    })
+}
+
+/// Check every input vector only sets declared signals
+fn check_input_vectors(ir_content: &str, vectors: &[HashMap<String, serde_json::Value>]) -> Result<()> {
+    let ir = load_ir(ir_content)
+        .map_err(|e| CompileError::emission(format!("IR load error: {:?}", e)))?;
+    let signals: Vec<&str> = ir.module.signals.iter().flatten().map(|s| s.name.as_str()).collect();
+    for (index, vector) in vectors.iter().enumerate() {
+        let mut names: Vec<&String> = vector.keys().collect();
+        names.sort();
+        if let Some(name) = names.into_iter().find(|name| !signals.contains(&name.as_str())) {
+            return Err(CompileError::emission(format!(
+                "Input vector {} sets undeclared signal '{}'", index + 1, name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// JSON value a signal of a known declared type takes as input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputKind {
+    Boolean,
+    Number,
+}
+
+impl InputKind {
+    fn of(type_: Option<&str>) -> Option<Self> {
+        match type_ {
+            None => Some(InputKind::Boolean),
+            Some(type_) if crate::typecheck::is_boolean_type(type_) => Some(InputKind::Boolean),
+            Some(type_) if crate::typecheck::is_numeric_type(type_) => Some(InputKind::Number),
+            Some(_) => None,
+        }
+    }
+    
+    fn accepts(self, value: &serde_json::Value) -> bool {
+        match self {
+            InputKind::Boolean => value.is_boolean(),
+            InputKind::Number => value.is_number(),
+        }
+    }
+    
+    fn describe(self) -> &'static str {
+        match self {
+            InputKind::Boolean => "a boolean",
+            InputKind::Number => "a number",
+        }
+    }
+}
+
+/// Boolean inputs for each scan, as the VM takes them
+type ScanInputs = Vec<HashMap<String, bool>>;
+
+/// Check each input value against its signal's declared type and keep the
+/// boolean values the VM scans with, warning about the numbers it drops;
+/// undeclared names must be booleans
+fn typed_input_vectors(ir_content: &str, vectors: Vec<HashMap<String, serde_json::Value>>) -> Result<(ScanInputs, Vec<Diagnostic>)> {
+    let ir = load_ir(ir_content)
+        .map_err(|e| CompileError::emission(format!("IR load error: {:?}", e)))?;
+    let types: HashMap<&str, Option<&str>> = ir.module.signals.iter().flatten()
+        .map(|s| (s.name.as_str(), s.type_.as_deref()))
+        .collect();
     
+    let mut typed = Vec::with_capacity(vectors.len());
+    let mut dropped = BTreeSet::new();
+    for vector in vectors {
+        let mut names: Vec<&String> = vector.keys().collect();
+        names.sort();
+        for name in names {
+            let value = &vector[name];
+            let type_ = types.get(name.as_str()).copied().flatten();
+            let Some(kind) = InputKind::of(type_) else {
+                return Err(CompileError::Type(format!(
+                    "signal '{}' is declared as {}, which run cannot supply", name, type_.unwrap_or_default()
+                )));
+            };
+            if !kind.accepts(value) {
+                return Err(CompileError::Type(format!("signal '{}' expects {}, got {}", name, kind.describe(), value)));
+            }
+            if kind == InputKind::Number {
+                dropped.insert(name.clone());
+            }
+        }
+        typed.push(vector.into_iter().filter_map(|(name, value)| Some((name, value.as_bool()?))).collect());
+    }
+    let warnings = dropped.into_iter()
+        .map(|name| Diagnostic::warning(format!(
+            "input '{}' is not passed to the VM, which only scans boolean inputs", name
+        )))
+        .collect();
+    Ok((typed, warnings))
+}
+
+/// Run one scan per input vector on a single VM and list coil states in
+/// declaration order, numbering the scans when there is more than one
+fn run_report(ir_content: &str, vectors: Vec<HashMap<String, bool>>, initial_json: Option<&str>) -> Result<String> {
     // Load IR
-    let ir = load_ir(&ir_content)
-        .map_err(|e| CompileError::Emission(format!("IR load error: {:?}", e)))?;
+    let ir = load_ir(ir_content)
+        .map_err(|e| CompileError::emission(format!("IR load error: {:?}", e)))?;
     
-    // Create VM and load program
+    // Create VM and load program, keeping the IR for advisories
     let mut vm = VM::new();
-    vm.load_program(ir)
-        .map_err(|e| CompileError::Emission(format!("VM load error: {:?}", e)))?;
+    vm.load_program(ir.clone())
+        .map_err(|e| CompileError::emission(format!("VM load error: {:?}", e)))?;
     
-    // Parse inputs
-    let mut inputs = HashMap::new();
-    if let Some(inputs_str) = inputs_json {
-        let parsed: HashMap<String, bool> = serde_json::from_str(inputs_str)
-            .map_err(|e| CompileError::Emission(format!("Invalid inputs JSON: {}", e)))?;
-        inputs = parsed;
+    let mut seeds: HashMap<String, bool> = HashMap::new();
+    if let Some(initial_str) = initial_json {
+        seeds = serde_json::from_str(initial_str)
+            .map_err(|e| CompileError::emission(format!("Invalid initial JSON: {}", e)))?;
     }
     
-    // Execute cycle
-    let outputs = vm.step(inputs)
-        .map_err(|e| CompileError::Emission(format!("VM execution error: {:?}", e)))?;
+    for advisory in ineffective_input_advisories(&ir, &vectors) {
+        eprintln!("{}", advisory);
+    }
+    let scans = vectors.len();
+    let mut report = String::new();
+    for (index, mut inputs) in vectors.into_iter().enumerate() {
+        // Seeds are supplied with the first scan's inputs only
+        if index == 0 {
+            inputs.extend(seeds.clone());
+        }
+        
+        // Execute cycle
+        let outputs = vm.step(inputs)
+            .map_err(|e| CompileError::emission(format!("VM execution error: {:?}", e)))?;
+        if index == 0 {
+            for advisory in seed_advisories(&ir, &seeds, &outputs) {
+                eprintln!("{}", advisory);
+            }
+        }
+        
+        // Display results
+        if scans == 1 {
+            report.push_str("Coil states:\n");
+        } else {
+            report.push_str(&format!("Scan {}:\n", index + 1));
+        }
+        for coil in ir.module.coils.iter().flatten() {
+            let value = outputs.get(&coil.name).copied().unwrap_or(false);
+            report.push_str(&format!("  {}: {}\n", coil.name, value));
+        }
+    }
+    Ok(report)
+}
+
+/// Compile `source` without writing IR, run one cycle and list coil states
+/// in declaration order
+fn exec_report(source: &str, inputs_json: Option<&str>) -> Result<String> {
+    let (module, ir) = compile_in_memory(source)?;
     
-    // Display results
-    println!("Coil states:");
-    for (name, value) in &outputs {
-        println!("  {}: {}", name, value);
+    let inputs: HashMap<String, bool> = match inputs_json {
+        Some(inputs_str) => serde_json::from_str(inputs_str)
+            .map_err(|e| CompileError::emission(format!("Invalid inputs JSON: {}", e)))?,
+        None => HashMap::new(),
+    };
+    let outputs = single_scan(&ir, inputs)?;
+    
+    let mut report = "Coil states:\n".to_string();
+    for coil in &module.coils {
+        let value = outputs.get(&coil.name).copied().unwrap_or(false);
+        report.push_str(&format!("  {}: {}\n", coil.name, value));
+    }
+    Ok(report)
+}
+
+/// Input count beyond which enumerating every combination is impractical
+const MAX_TRUTH_TABLE_INPUTS: usize = 12;
+
+/// Run one scan from a fresh VM for each input combination and tabulate coil states
+fn truth_table_report(ir_content: &str) -> Result<String> {
+    let ir = load_ir(ir_content)
+        .map_err(|e| CompileError::emission(format!("IR load error: {:?}", e)))?;
+    let signals: Vec<String> = ir.module.signals.iter().flatten().map(|s| s.name.clone()).collect();
+    let coils: Vec<String> = ir.module.coils.iter().flatten().map(|c| c.name.clone()).collect();
+    if signals.len() > MAX_TRUTH_TABLE_INPUTS {
+        return Err(CompileError::emission(format!(
+            "Truth table supports at most {} inputs, module has {}",
+            MAX_TRUTH_TABLE_INPUTS, signals.len()
+        )));
     }
     
-    Ok(())
+    let row = |cells: Vec<String>| -> String {
+        let widths = signals.iter().chain(&coils).map(|name| name.len());
+        let padded: Vec<String> = cells.iter().zip(widths).map(|(cell, width)| format!("{:<1$}", cell, width)).collect();
+        let (inputs, outputs) = padded.split_at(signals.len());
+        let line = format!("{} | {}", inputs.join(" "), outputs.join(" "));
+        format!("{}\n", line.trim_end())
+    };
+    
+    let mut report = row(signals.iter().chain(&coils).cloned().collect());
+    for inputs in input_combinations(&signals) {
+        let outputs = single_scan(ir_content, inputs.clone())?;
+        
+        let bit = |value: bool| if value { "1" } else { "0" }.to_string();
+        let cells = signals.iter().map(|name| bit(inputs[name]))
+            .chain(coils.iter().map(|name| bit(outputs.get(name).copied().unwrap_or(false))))
+            .collect();
+        report.push_str(&row(cells));
+    }
+    Ok(report)
+}
+
+/// IR the VM runs as written: `else` branches, explicit values and `set`/`reset`
+/// only reach it once lowered to primitive rungs and actions
+fn emit_runnable_ir(module: &crate::ast::Module) -> Result<String> {
+    crate::emit_ir_with(module, &EmitOptions { lower: true, ..Default::default() })
+}
+
+/// Every assignment of true/false to `signals`, counting up in binary with the
+/// first signal as the most significant bit
+fn input_combinations(signals: &[String]) -> impl Iterator<Item = HashMap<String, bool>> + '_ {
+    (0..1usize << signals.len()).map(move |combination| {
+        signals.iter().enumerate()
+            .map(|(i, name)| (name.clone(), combination & (1 << (signals.len() - 1 - i)) != 0))
+            .collect()
+    })
+}
+
+/// Run one scan of the program from a freshly loaded VM
+fn single_scan(ir_content: &str, inputs: HashMap<String, bool>) -> Result<HashMap<String, bool>> {
+    let program = load_ir(ir_content)
+        .map_err(|e| CompileError::emission(format!("IR load error: {:?}", e)))?;
+    let mut vm = VM::new();
+    vm.load_program(program)
+        .map_err(|e| CompileError::emission(format!("VM load error: {:?}", e)))?;
+    vm.step(inputs)
+        .map_err(|e| CompileError::emission(format!("VM execution error: {:?}", e)))
+}
+
+/// Check that no two of the `exclusive` coils are energised together over
+/// every input combination (single scan each).
+///
+/// Returns `None` when the interlock holds, otherwise the first input vector
+/// on which two of the coils are both energised.
+fn interlock_check(ir_content: &str, exclusive: &[String]) -> Result<Option<String>> {
+    let ir = load_ir(ir_content)
+        .map_err(|e| CompileError::emission(format!("IR load error: {:?}", e)))?;
+    let coils: Vec<&str> = ir.module.coils.iter().flatten().map(|c| c.name.as_str()).collect();
+    if exclusive.len() < 2 {
+        return Err(CompileError::emission("Interlock check needs at least two coils".to_string()));
+    }
+    if let Some(unknown) = exclusive.iter().find(|name| !coils.contains(&name.as_str())) {
+        return Err(CompileError::name_resolution(format!("Undefined coil: {}", unknown)));
+    }
+    let signals: Vec<String> = ir.module.signals.iter().flatten().map(|s| s.name.clone()).collect();
+    if signals.len() > MAX_TRUTH_TABLE_INPUTS {
+        return Err(CompileError::emission(format!(
+            "Interlock check supports at most {} inputs, module has {}",
+            MAX_TRUTH_TABLE_INPUTS, signals.len()
+        )));
+    }
+    
+    for inputs in input_combinations(&signals) {
+        let outputs = single_scan(ir_content, inputs.clone())?;
+        let energised: Vec<&String> = exclusive.iter()
+            .filter(|coil| outputs.get(*coil).copied().unwrap_or(false))
+            .collect();
+        if let [first, second, ..] = energised.as_slice() {
+            let vector: Vec<String> = signals.iter().map(|s| format!("{}={}", s, inputs[s])).collect();
+            return Ok(Some(format!(
+                "coils '{}' and '{}' are both energised for inputs {}",
+                first, second, vector.join(", ")
+            )));
+        }
+    }
+    Ok(None)
+}
+
+/// Compare two modules over every input combination (single scan each).
+///
+/// Returns `None` when equivalent, otherwise a description of the first
+/// input vector on which some coil differs.
+fn equiv_check(source_a: &str, source_b: &str) -> Result<Option<String>> {
+    let compile = |source: &str| -> Result<(crate::ast::Module, String)> {
+        let mut module = parse(source)?;
+        resolve_names(&mut module)?;
+        let ir = emit_runnable_ir(&module)?;
+        Ok((module, ir))
+    };
+    let (module_a, ir_a) = compile(source_a)?;
+    let (module_b, ir_b) = compile(source_b)?;
+    
+    let names = |declared: Vec<&String>| -> Vec<String> {
+        let mut names: Vec<String> = declared.into_iter().cloned().collect();
+        names.sort();
+        names.dedup();
+        names
+    };
+    let signals = names(module_a.signals.iter().chain(&module_b.signals).map(|s| &s.name).collect());
+    let coils = names(module_a.coils.iter().chain(&module_b.coils).map(|c| &c.name).collect());
+    if signals.len() > MAX_TRUTH_TABLE_INPUTS {
+        return Err(CompileError::emission(format!(
+            "Equivalence check supports at most {} inputs, modules have {}",
+            MAX_TRUTH_TABLE_INPUTS, signals.len()
+        )));
+    }
+    
+    for inputs in input_combinations(&signals) {
+        let outputs_a = single_scan(&ir_a, inputs.clone())?;
+        let outputs_b = single_scan(&ir_b, inputs.clone())?;
+        for coil in &coils {
+            let a = outputs_a.get(coil).copied().unwrap_or(false);
+            let b = outputs_b.get(coil).copied().unwrap_or(false);
+            if a != b {
+                let vector: Vec<String> = signals.iter().map(|s| format!("{}={}", s, inputs[s])).collect();
+                return Ok(Some(format!(
+                    "coil '{}' is {} in A but {} in B for inputs {}",
+                    coil, a, b, vector.join(", ")
+                )));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Warn about supplied inputs that no coil depends on, which usually means
+/// the wrong signal name is being driven
+fn ineffective_input_advisories(ir: &charta_core::ir::schema::IR, vectors: &[HashMap<String, bool>]) -> Vec<Diagnostic> {
+    let read: HashSet<String> = ir_coil_dependencies(ir).into_values().flatten().collect();
+    let supplied: BTreeSet<&String> = vectors.iter().flat_map(|vector| vector.keys()).collect();
+    supplied.into_iter()
+        .filter(|name| !read.contains(*name))
+        .map(|name| Diagnostic::warning(format!(
+            "input '{}' has no effect: no rung driving a coil reads it", name
+        )))
+        .collect()
+}
+
+/// Warn about seeds on non-latching coils that the first scan overwrote
+fn seed_advisories(ir: &charta_core::ir::schema::IR, seeds: &HashMap<String, bool>, outputs: &HashMap<String, bool>) -> Vec<Diagnostic> {
+    ir.module.coils.iter().flatten()
+        .filter(|coil| !coil.latching.unwrap_or(false))
+        .filter_map(|coil| {
+            let seed = *seeds.get(&coil.name)?;
+            let scanned = outputs.get(&coil.name).copied().unwrap_or(false);
+            (seed != scanned).then(|| Diagnostic::warning(format!(
+                "initial value {} for coil '{}' has no effect: it is not latching and the first scan sets it to {}",
+                seed, coil.name, scanned
+            )))
+        })
+        .collect()
 }
 
 fn validate_command(input: &PathBuf) -> Result<()> {
@@ -136,10 +983,16 @@ fn validate_command(input: &PathBuf) -> Result<()> {
         .map_err(CompileError::Io)?;
     
     // Parse
-    let mut module = parse(&source)?;
+    let (mut module, mut warnings) = parse_with_warnings(&source)?;
     
     // Resolve names
-    resolve_names(&mut module)?;
+    warnings.extend(resolve_names(&mut module)?);
+    for warning in &warnings {
+        eprintln!("{}", warning);
+    }
+    
+    // Check types
+    check_types(&module)?;
     
     // Emit IR
     let ir_json = emit_ir(&module)?;
@@ -147,50 +1000,582 @@ fn validate_command(input: &PathBuf) -> Result<()> {
     // Validate IR against schema
     let schema_path = "../../spec/ir-schema.json";
     validate_ir(&ir_json, schema_path)
-        .map_err(|e| CompileError::Emission(format!("IR validation error: {:?}", e)))?;
+        .map_err(|e| CompileError::emission(format!("IR validation error: {:?}", e)))?;
     
-    println!("Validation successful: {}", input.display());
+    // Cross-check references within the emitted IR
+    let ir: charta_core::ir::schema::IR = serde_json::from_str(&ir_json)
+        .map_err(|e| CompileError::emission(format!("Invalid IR JSON: {}", e)))?;
+    validate_ir_semantics(&ir)?;
+    
+    eprintln!("Validation successful: {}", input.display());
     Ok(())
 }
 
-fn inspect_command(input: &PathBuf) -> Result<()> {
-    let ir_content = fs::read_to_string(input)
-        .map_err(CompileError::Io)?;
+fn inspect_command(input: &Path, order: InspectOrder) -> Result<()> {
+    let ir_content = read_ir(input)?;
     
     // Parse IR
     let ir: charta_core::ir::schema::IR = serde_json::from_str(&ir_content)
-        .map_err(|e| CompileError::Emission(format!("Invalid IR JSON: {}", e)))?;
+        .map_err(|e| CompileError::emission(format!("Invalid IR JSON: {}", e)))?;
     
-    // Display IR structure
-    println!("Module: {}", ir.module.name);
+    print!("{}", inspect_report(&ir, order));
+    Ok(())
+}
+
+/// Render the IR structure grouped by kind
+fn inspect_report(ir: &charta_core::ir::schema::IR, order: InspectOrder) -> String {
+    fn ordered<T>(items: &[T], order: InspectOrder, name: impl Fn(&T) -> &str) -> Vec<&T> {
+        let mut items: Vec<&T> = items.iter().collect();
+        if order == InspectOrder::Alpha {
+            items.sort_by(|a, b| name(a).cmp(name(b)));
+        }
+        items
+    }
+    
+    let mut report = String::new();
+    report.push_str(&format!("Module: {}\n", ir.module.name));
     if let Some(context) = &ir.module.context {
-        println!("Context: {}", context);
+        report.push_str(&format!("Context: {}\n", context));
     }
     
     if let Some(signals) = &ir.module.signals {
-        println!("\nSignals ({}):", signals.len());
-        for signal in signals {
-            println!("  - {}", signal.name);
+        report.push_str(&format!("\nSignals ({}):\n", signals.len()));
+        for signal in ordered(signals, order, |s| &s.name) {
+            report.push_str(&format!("  - {}\n", signal.name));
         }
     }
     
     if let Some(coils) = &ir.module.coils {
-        println!("\nCoils ({}):", coils.len());
-        for coil in coils {
-            println!("  - {} (latching: {}, critical: {})",
+        report.push_str(&format!("\nCoils ({}):\n", coils.len()));
+        for coil in ordered(coils, order, |c| &c.name) {
+            report.push_str(&format!("  - {} (latching: {}, critical: {})\n",
                 coil.name,
                 coil.latching.unwrap_or(false),
                 coil.critical.unwrap_or(false)
-            );
+            ));
         }
     }
     
     if let Some(rungs) = &ir.module.rungs {
-        println!("\nRungs ({}):", rungs.len());
-        for rung in rungs {
-            println!("  - {}", rung.name);
+        report.push_str(&format!("\nRungs ({}):\n", rungs.len()));
+        for rung in ordered(rungs, order, |r| &r.name) {
+            report.push_str(&format!("  - {}\n", rung.name));
         }
     }
     
+    report
+}
+
+fn fmt_command(input: &PathBuf, check: bool) -> Result<()> {
+    let source = fs::read_to_string(input)
+        .map_err(CompileError::Io)?;
+    let formatted = crate::formatter::format(&source)?;
+    if formatted == source {
+        return Ok(());
+    }
+    if check {
+        eprintln!("{} is not formatted", input.display());
+        return Err(CompileError::Aborted(1));
+    }
+    fs::write(input, formatted).map_err(CompileError::Io)
+}
+
+fn bench_command(input: &PathBuf, iterations: usize, json: bool) -> Result<()> {
+    let source = fs::read_to_string(input)
+        .map_err(CompileError::Io)?;
+    
+    print!("{}", bench_report(&source, iterations, json)?);
     Ok(())
 }
+
+/// Wall-clock time spent in each compiler phase for a single compile
+struct PhaseTimings {
+    parse: Duration,
+    resolve: Duration,
+    emit: Duration,
+}
+
+impl PhaseTimings {
+    fn phases(&self) -> [(&'static str, Duration); 3] {
+        [("parse", self.parse), ("resolve", self.resolve), ("emit", self.emit)]
+    }
+}
+
+fn time_phases(source: &str) -> Result<PhaseTimings> {
+    let start = Instant::now();
+    let mut module = parse(source)?;
+    let parse_time = start.elapsed();
+    
+    let start = Instant::now();
+    resolve_names(&mut module)?;
+    let resolve_time = start.elapsed();
+    
+    let start = Instant::now();
+    emit_ir(&module)?;
+    let emit_time = start.elapsed();
+    
+    Ok(PhaseTimings {
+        parse: parse_time,
+        resolve: resolve_time,
+        emit: emit_time,
+    })
+}
+
+/// Compile `source` `iterations` times and report min/median/max per phase
+fn bench_report(source: &str, iterations: usize, json: bool) -> Result<String> {
+    if iterations == 0 {
+        return Err(CompileError::emission("Iterations must be at least 1".to_string()));
+    }
+    
+    let mut runs = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        runs.push(time_phases(source)?);
+    }
+    
+    let mut stats = Vec::new();
+    for (index, (phase, _)) in runs[0].phases().iter().enumerate() {
+        let mut samples: Vec<Duration> = runs.iter().map(|t| t.phases()[index].1).collect();
+        samples.sort();
+        stats.push((*phase, samples[0], samples[samples.len() / 2], samples[samples.len() - 1]));
+    }
+    
+    if json {
+        let mut phases_json = serde_json::Map::new();
+        for (phase, min, median, max) in &stats {
+            phases_json.insert(phase.to_string(), serde_json::json!({
+                "min_us": min.as_secs_f64() * 1e6,
+                "median_us": median.as_secs_f64() * 1e6,
+                "max_us": max.as_secs_f64() * 1e6,
+            }));
+        }
+        let report = serde_json::json!({
+            "iterations": iterations,
+            "phases": phases_json,
+        });
+        let mut out = serde_json::to_string_pretty(&report)
+            .map_err(|e| CompileError::emission(format!("JSON serialization error: {}", e)))?;
+        out.push('\n');
+        Ok(out)
+    } else {
+        let mut out = format!("Iterations: {}\n", iterations);
+        for (phase, min, median, max) in &stats {
+            out.push_str(&format!("  {:<8} min {:>10.1?}  median {:>10.1?}  max {:>10.1?}\n",
+                phase, min, median, max));
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+module bench_module
+
+signal input
+coil output
+
+rung r1:
+  when NO input
+  then energise output
+"#;
+
+    #[test]
+    fn test_compile_to_stdout_keeps_warnings_on_stderr() {
+        let input = std::env::temp_dir().join("charta_cli_stdout_test.charta");
+        fs::write(&input, "module no_rungs\nsignal input\n").unwrap();
+        
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        compile_to(&input, Some(&PathBuf::from("-")), &CompileOptions::default(), &mut out, &mut err).unwrap();
+        fs::remove_file(&input).unwrap();
+        
+        let out = String::from_utf8(out).unwrap();
+        let err = String::from_utf8(err).unwrap();
+        let ir: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(ir["module"]["name"], "no_rungs");
+        assert!(err.contains("warning: module 'no_rungs' has no rungs"));
+        assert!(!out.contains("warning"));
+    }
+
+    #[test]
+    fn test_failed_emit_keeps_previous_output() {
+        struct Failing;
+        impl crate::Emitter for Failing {
+            fn emit(&self, _module: &crate::ast::Module) -> Result<String> {
+                Err(CompileError::emission("unsupported".to_string()))
+            }
+            
+            fn extension(&self) -> &str {
+                "fail"
+            }
+        }
+        crate::register_emitter("failing", Box::new(Failing)).unwrap();
+        
+        let dir = scratch_dir("failed_emit");
+        let input = dir.join("m.charta");
+        let output = dir.join("m.ir.json");
+        fs::write(&input, "module m\nsignal a\ncoil x\nrung r:\n  when NO a\n  then energise x\n").unwrap();
+        fs::write(&output, "previous").unwrap();
+        
+        let options = CompileOptions { format: "failing".to_string(), ..Default::default() };
+        let result = compile_to(&input, Some(&output), &options, &mut io::sink(), &mut io::sink());
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&output).unwrap(), "previous");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        
+        compile_to(&input, Some(&output), &CompileOptions::default(), &mut io::sink(), &mut io::sink()).unwrap();
+        assert!(fs::read_to_string(&output).unwrap().contains("\"name\": \"m\""));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_strict_fails_on_warnings() {
+        let input = std::env::temp_dir().join("charta_cli_strict_test.charta");
+        fs::write(&input, "module no_rungs\nsignal input\n").unwrap();
+        
+        let output = PathBuf::from("-");
+        compile_to(&input, Some(&output), &CompileOptions::default(), &mut io::sink(), &mut io::sink()).unwrap();
+        
+        let strict = CompileOptions { resolve: ResolveOptions { strict: true, ..Default::default() }, ..Default::default() };
+        let mut err = Vec::new();
+        let result = compile_to(&input, Some(&output), &strict, &mut io::sink(), &mut err);
+        fs::remove_file(&input).unwrap();
+        
+        assert!(matches!(result, Err(CompileError::Aborted(1))));
+        assert!(String::from_utf8(err).unwrap().contains("error: Type error: module 'no_rungs' has no rungs"));
+        
+        // Parser lints count too
+        fs::write(&input, "module m\nsignal a\nsignal b\ncoil x\nrung r:\n  when NOT NO a AND NO b\n  then energise x\n").unwrap();
+        compile_to(&input, Some(&output), &CompileOptions::default(), &mut io::sink(), &mut io::sink()).unwrap();
+        let mut err = Vec::new();
+        let result = compile_to(&input, Some(&output), &strict, &mut io::sink(), &mut err);
+        fs::remove_file(&input).unwrap();
+        
+        assert!(matches!(result, Err(CompileError::Aborted(1))));
+        let err = String::from_utf8(err).unwrap();
+        assert!(err.contains("error at line 6, column 8: `NOT` applies only to the contact that follows it"), "{}", err);
+    }
+
+    #[test]
+    fn test_compile_reports_type_errors() {
+        let input = std::env::temp_dir().join("charta_cli_typecheck_test.charta");
+        fs::write(&input, "module m\nsignal ready: bool\ncoil pump\nrung r:\n  when ready > 1\n  then energise pump\n").unwrap();
+        
+        let mut err = Vec::new();
+        let result = compile_to(&input, Some(&PathBuf::from("-")), &CompileOptions::default(), &mut io::sink(), &mut err);
+        fs::remove_file(&input).unwrap();
+        
+        assert!(matches!(result, Err(CompileError::Aborted(1))));
+        assert!(String::from_utf8(err).unwrap().contains("error: Type error: Comparison `ready > 1` needs a numeric signal"));
+    }
+
+    #[test]
+    fn test_fmt_checks_and_rewrites() {
+        let input = std::env::temp_dir().join("charta_cli_fmt_test.charta");
+        fs::write(&input, "module m\nsignal a coil x\nrung r: when NO a then energise x\n").unwrap();
+        
+        assert!(matches!(fmt_command(&input, true), Err(CompileError::Aborted(1))));
+        fmt_command(&input, false).unwrap();
+        let formatted = fs::read_to_string(&input).unwrap();
+        let checked = fmt_command(&input, true);
+        fs::remove_file(&input).unwrap();
+        
+        assert_eq!(formatted, "module m\nsignal a\ncoil x\n\nrung r:\n  when NO a\n  then energise x\n");
+        assert!(checked.is_ok());
+    }
+
+    /// Fresh scratch directory under the system temp dir
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("charta_cli_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_batch_progress_respects_quiet() {
+        let dir = scratch_dir("progress");
+        for name in ["a", "b"] {
+            fs::write(dir.join(format!("{}.charta", name)), format!("library module {}\nsignal s\n", name)).unwrap();
+        }
+        
+        let mut err = Vec::new();
+        compile_dir(&dir, &CompileOptions::default(), true, &mut err).unwrap();
+        let err = String::from_utf8(err).unwrap();
+        assert!(err.contains("[1/2]"));
+        assert!(err.contains("[2/2]"));
+        
+        let quiet = CompileOptions { quiet: true, ..Default::default() };
+        let mut err = Vec::new();
+        compile_dir(&dir, &quiet, true, &mut err).unwrap();
+        assert!(String::from_utf8(err).unwrap().is_empty());
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_batch_rejects_duplicate_module_names() {
+        let dir = scratch_dir("duplicate_modules");
+        fs::write(dir.join("a.charta"), "library module foo\n").unwrap();
+        fs::write(dir.join("b.charta"), "// same name\nlibrary module foo\n").unwrap();
+        
+        let mut err = Vec::new();
+        let result = compile_dir(&dir, &CompileOptions::default(), false, &mut err);
+        let err = String::from_utf8(err).unwrap();
+        assert!(result.is_err());
+        assert!(err.contains(&format!(
+            "module 'foo' is declared in both {} and {}",
+            dir.join("a.charta").display(), dir.join("b.charta").display()
+        )), "{}", err);
+        assert!(!dir.join("a.ir.json").exists());
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_batch_out_dir_mirrors_layout() {
+        let dir = scratch_dir("out_dir_src");
+        let out_dir = scratch_dir("out_dir_dst");
+        fs::create_dir_all(dir.join("line1/pumps")).unwrap();
+        fs::write(dir.join("top.charta"), "library module top\n").unwrap();
+        fs::write(dir.join("line1/pumps/feed.charta"), "library module feed\n").unwrap();
+        
+        let options = CompileOptions { quiet: true, out_dir: Some(out_dir.clone()), ..Default::default() };
+        compile_dir(&dir, &options, false, &mut io::sink()).unwrap();
+        
+        assert!(out_dir.join("top.ir.json").is_file());
+        assert!(out_dir.join("line1/pumps/feed.ir.json").is_file());
+        assert!(!dir.join("top.ir.json").exists());
+        
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn test_dry_run_writes_nothing() {
+        let dir = scratch_dir("dry_run");
+        let input = dir.join("plant.charta");
+        fs::write(&input, "library module plant\n").unwrap();
+        
+        let options = CompileOptions { dry_run: true, out_dir: Some(dir.join("out")), ..Default::default() };
+        let output = out_dir_path(&dir.join("out"), Path::new("plant.charta"), &options).unwrap();
+        let mut err = Vec::new();
+        compile_to(&input, Some(&output), &options, &mut io::sink(), &mut err).unwrap();
+        
+        let err = String::from_utf8(err).unwrap();
+        assert!(err.starts_with(&format!("Would write {}", output.display())), "{}", err);
+        let entries: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(entries, ["plant.charta"]);
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bench_reports_each_phase() {
+        let report = bench_report(SOURCE, 1, false).unwrap();
+        assert!(report.contains("Iterations: 1"));
+        for phase in ["parse", "resolve", "emit"] {
+            assert!(report.contains(phase), "missing {} timing", phase);
+        }
+        
+        let json: serde_json::Value = serde_json::from_str(&bench_report(SOURCE, 1, true).unwrap()).unwrap();
+        assert_eq!(json["iterations"], 1);
+        for phase in ["parse", "resolve", "emit"] {
+            assert!(json["phases"][phase]["median_us"].is_number());
+        }
+    }
+
+    #[test]
+    fn test_inspect_order() {
+        let mut module = parse("module m\ncoil zeta\ncoil alpha\ncoil mid\nsignal s\nrung r:\n  when NO s\n  then energise zeta").unwrap();
+        resolve_names(&mut module).unwrap();
+        let ir: charta_core::ir::schema::IR = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        
+        let coil_lines = |report: String| -> Vec<String> {
+            report.lines()
+                .skip_while(|line| !line.starts_with("Coils"))
+                .skip(1)
+                .take_while(|line| line.starts_with("  - "))
+                .map(|line| line[4..].split(' ').next().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(coil_lines(inspect_report(&ir, InspectOrder::Source)), ["zeta", "alpha", "mid"]);
+        assert_eq!(coil_lines(inspect_report(&ir, InspectOrder::Alpha)), ["alpha", "mid", "zeta"]);
+    }
+
+    #[test]
+    fn test_truth_table_two_inputs() {
+        let mut module = parse("module m\nsignal a\nsignal b\ncoil both\nrung r:\n  when NO a AND NO b\n  then energise both").unwrap();
+        resolve_names(&mut module).unwrap();
+        let report = truth_table_report(&emit_ir(&module).unwrap()).unwrap();
+        
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines, ["a b | both", "0 0 | 0", "0 1 | 0", "1 0 | 0", "1 1 | 1"]);
+    }
+
+    #[test]
+    fn test_github_message_format() {
+        let dir = scratch_dir("github_format");
+        let input = dir.join("broken.charta");
+        fs::write(&input, "module m\nsignal a\ncoil x\nrung r: when NO a then energise x(,)\n").unwrap();
+        
+        let options = CompileOptions { message_format: MessageFormat::Github, ..Default::default() };
+        let mut err = Vec::new();
+        assert!(compile_to(&input, None, &options, &mut io::sink(), &mut err).is_err());
+        
+        let err = String::from_utf8(err).unwrap();
+        let expected = format!("::error file={},line=4,col=35::Expected expression, found Comma", input.display());
+        assert_eq!(err.trim_end(), expected);
+    }
+
+    #[test]
+    fn test_ineffective_seed_advisory() {
+        let mut module = parse("module m\nsignal a\ncoil x\ncoil held [latching]\nrung r:\n  when NO a\n  then energise x").unwrap();
+        resolve_names(&mut module).unwrap();
+        let ir_content = emit_ir(&module).unwrap();
+        
+        let mut vm = VM::new();
+        vm.load_program(load_ir(&ir_content).unwrap()).unwrap();
+        let outputs = vm.step(HashMap::from([("a".to_string(), false)])).unwrap();
+        
+        let seeds = HashMap::from([("x".to_string(), true), ("held".to_string(), true)]);
+        let advisories = seed_advisories(&load_ir(&ir_content).unwrap(), &seeds, &outputs);
+        assert_eq!(advisories.len(), 1);
+        assert!(advisories[0].message.contains("coil 'x' has no effect"), "{}", advisories[0]);
+    }
+
+    #[test]
+    fn test_ineffective_input_advisory() {
+        let (_, ir_content) = compile_in_memory("module m\nsignal a\nsignal spare\ncoil x\nrung r:\n  when NO a\n  then energise x").unwrap();
+        let vectors = vec![HashMap::from([("a".to_string(), true), ("spare".to_string(), true)])];
+        let advisories = ineffective_input_advisories(&load_ir(&ir_content).unwrap(), &vectors);
+        assert_eq!(advisories.len(), 1);
+        assert_eq!(advisories[0].message, "input 'spare' has no effect: no rung driving a coil reads it");
+    }
+
+    #[test]
+    fn test_exec_runs_source_directly() {
+        let source = "module m\nsignal start\nsignal stop\ncoil motor\ncoil alarm\nrung run:\n  when NO start AND NC stop\n  then energise motor\nrung trip:\n  when NO stop\n  then energise alarm\n";
+        let report = exec_report(source, Some(r#"{"start": true, "stop": false}"#)).unwrap();
+        assert_eq!(report, "Coil states:\n  motor: true\n  alarm: false\n");
+        
+        let branched = "module m\nsignal a\ncoil x\ncoil y\nrung r:\n  when NO a\n  then energise x\n  else energise y\n  end\n";
+        assert_eq!(exec_report(branched, Some(r#"{"a": false}"#)).unwrap(), "Coil states:\n  x: false\n  y: true\n");
+        let err = exec_report("module m\nsignal level: real\ncoil x\nrung r:\n  when level > 5\n  then energise x\n", None).unwrap_err();
+        assert!(err.to_string().contains("has no primitive form"), "{}", err);
+    }
+
+    #[test]
+    fn test_run_reads_stdin() {
+        let source = "module piped\nsignal start\ncoil motor\nrung r:\n  when NO start\n  then energise motor\n";
+        let mut compiled = Vec::new();
+        let input = std::env::temp_dir().join("charta_cli_stdin_test.charta");
+        fs::write(&input, source).unwrap();
+        compile_to(&input, Some(&PathBuf::from("-")), &CompileOptions::default(), &mut compiled, &mut io::sink()).unwrap();
+        fs::remove_file(&input).unwrap();
+        
+        let stdin = Path::new("-");
+        let ir_content = read_run_input(stdin, false, &mut compiled.as_slice()).unwrap();
+        let (vectors, _) = typed_input_vectors(&ir_content, input_vectors(r#"{"start": true}"#).unwrap()).unwrap();
+        assert_eq!(run_report(&ir_content, vectors, None).unwrap(), "Coil states:\n  motor: true\n");
+        
+        let ir_content = read_run_input(stdin, true, &mut source.as_bytes()).unwrap();
+        let (vectors, _) = typed_input_vectors(&ir_content, input_vectors(r#"{"start": false}"#).unwrap()).unwrap();
+        assert_eq!(run_report(&ir_content, vectors, None).unwrap(), "Coil states:\n  motor: false\n");
+    }
+
+    #[test]
+    fn test_run_replays_input_vectors() {
+        let source = "module replay\nsignal start\nsignal stop\ncoil motor\ncoil alarm\nrung run:\n  when NO start AND NC stop\n  then energise motor\nrung trip:\n  when NO stop\n  then energise alarm\n";
+        let (_, ir_content) = compile_in_memory(source).unwrap();
+        let vectors = input_vectors(r#"[{"start": false}, {"start": true}, {"start": true, "stop": true}]"#).unwrap();
+        check_input_vectors(&ir_content, &vectors).unwrap();
+        let (vectors, _) = typed_input_vectors(&ir_content, vectors).unwrap();
+        assert_eq!(run_report(&ir_content, vectors, None).unwrap(), concat!(
+            "Scan 1:\n  motor: false\n  alarm: false\n",
+            "Scan 2:\n  motor: true\n  alarm: false\n",
+            "Scan 3:\n  motor: false\n  alarm: true\n",
+        ));
+        
+        let typo = input_vectors(r#"[{"start": true}, {"strat": true}]"#).unwrap();
+        let err = check_input_vectors(&ir_content, &typo).unwrap_err();
+        assert!(err.to_string().contains("Input vector 2 sets undeclared signal 'strat'"), "{}", err);
+    }
+
+    #[test]
+    fn test_run_inputs_match_signal_types() {
+        let source = "module tank\nsignal level: real\nsignal start\nsignal mode: text\ncoil pump\nrung r:\n  when NO start\n  then energise pump\n";
+        let (_, ir_content) = compile_in_memory(source).unwrap();
+        
+        let err = typed_input_vectors(&ir_content, input_vectors(r#"{"level": true}"#).unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "Type error: signal 'level' expects a number, got true");
+        let err = typed_input_vectors(&ir_content, input_vectors(r#"{"start": 1}"#).unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "Type error: signal 'start' expects a boolean, got 1");
+        
+        let err = typed_input_vectors(&ir_content, input_vectors(r#"{"mode": "auto"}"#).unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "Type error: signal 'mode' is declared as text, which run cannot supply");
+        
+        let (vectors, warnings) = typed_input_vectors(&ir_content, input_vectors(r#"[{"level": 2.5, "start": true}, {"level": 3}]"#).unwrap()).unwrap();
+        assert_eq!(vectors, vec![HashMap::from([("start".to_string(), true)]), HashMap::new()]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].to_string(), "warning: input 'level' is not passed to the VM, which only scans boolean inputs");
+    }
+
+    #[test]
+    fn test_ast_json() {
+        let source = "module tree\nsignal a\ncoil x\nrung on:\n  when NO a\n  then energise x\nrung off:\n  when NC a\n  then de_energise x\n";
+        let ast: serde_json::Value = serde_json::from_str(&ast_report(source, true, false).unwrap()).unwrap();
+        assert_eq!(ast["name"], "tree");
+        let rungs = ast["rungs"].as_array().unwrap();
+        assert_eq!(rungs.len(), 2);
+        assert_eq!(rungs[1]["name"], "off");
+        assert_eq!(rungs[1]["guard"]["Contact"]["kind"], serde_json::Value::Null);
+        
+        let resolved: serde_json::Value = serde_json::from_str(&ast_report(source, true, true).unwrap()).unwrap();
+        assert_eq!(resolved["rungs"][1]["guard"]["Contact"]["kind"], "Signal");
+    }
+
+    #[test]
+    fn test_equiv() {
+        let a = "module a\nsignal x\nsignal y\ncoil out\nrung r:\n  when NOT (NO x OR NO y)\n  then energise out";
+        let b = "module b\nsignal x\nsignal y\ncoil out\nrung r:\n  when NC x AND NC y\n  then energise out";
+        assert_eq!(equiv_check(a, b).unwrap(), None);
+        
+        let c = "module c\nsignal x\nsignal y\ncoil out\nrung r:\n  when NC x OR NC y\n  then energise out";
+        assert_eq!(
+            equiv_check(a, c).unwrap().unwrap(),
+            "coil 'out' is false in A but true in B for inputs x=false, y=true"
+        );
+        
+        let branched = "module a\nsignal x\ncoil out\ncoil idle\nrung r:\n  when NO x\n  then energise out\n  else energise idle\n  end";
+        let split = "module b\nsignal x\ncoil out\ncoil idle\nrung r:\n  when NO x\n  then energise out\nrung r_else:\n  when NC x\n  then energise idle";
+        assert_eq!(equiv_check(branched, split).unwrap(), None);
+    }
+
+    #[test]
+    fn test_interlock_check() {
+        let exclusive = vec!["forward".to_string(), "reverse".to_string()];
+        let interlocked = "module m\nsignal fwd\nsignal rev\ncoil forward\ncoil reverse\nrung f:\n  when NO fwd AND NC rev\n  then energise forward\nrung r:\n  when NO rev AND NC fwd\n  then energise reverse";
+        let (_, ir_content) = compile_in_memory(interlocked).unwrap();
+        assert_eq!(interlock_check(&ir_content, &exclusive).unwrap(), None);
+        
+        let broken = interlocked.replace(" AND NC rev", "").replace(" AND NC fwd", "");
+        let (_, ir_content) = compile_in_memory(&broken).unwrap();
+        assert_eq!(
+            interlock_check(&ir_content, &exclusive).unwrap().unwrap(),
+            "coils 'forward' and 'reverse' are both energised for inputs fwd=true, rev=true"
+        );
+    }
+
+    #[test]
+    fn test_tokens_report_positions() {
+        let report = tokens_report("module m\n\n  signal start\n");
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], "1:1 Module");
+        assert_eq!(lines[1], "1:8 Identifier(\"m\")");
+        assert_eq!(lines[2], "3:3 Signal");
+    }
+}
@@ -0,0 +1,85 @@
+use crate::ast::Module;
+
+/// Rust keywords, strict and reserved, that need a raw identifier as a field name
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+    "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized",
+    "use", "virtual", "where", "while", "yield",
+];
+
+/// Names that cannot be raw identifiers either, so get a trailing underscore
+const NON_RAW_NAMES: &[&str] = &["crate", "self", "super", "Self", "_"];
+
+/// Generate a Rust struct with one `bool` field per input signal, plus a
+/// `to_map()` producing the map the VM's `step` expects.
+pub fn generate_inputs_struct(module: &Module) -> String {
+    let name = format!("{}Inputs", camel_case(&module.name));
+    let mut code = String::new();
+    
+    code.push_str(&format!("/// Inputs for module `{}`, generated by `charta codegen-inputs`\n", module.name));
+    code.push_str("#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]\n");
+    code.push_str(&format!("pub struct {} {{\n", name));
+    for signal in &module.signals {
+        code.push_str(&format!("    pub {}: bool,\n", field_name(&signal.name)));
+    }
+    code.push_str("}\n\n");
+    
+    code.push_str(&format!("impl {} {{\n", name));
+    code.push_str("    pub fn to_map(&self) -> std::collections::HashMap<String, bool> {\n");
+    code.push_str("        let mut map = std::collections::HashMap::new();\n");
+    for signal in &module.signals {
+        code.push_str(&format!(
+            "        map.insert({:?}.to_string(), self.{});\n",
+            signal.name, field_name(&signal.name)
+        ));
+    }
+    code.push_str("        map\n");
+    code.push_str("    }\n");
+    code.push_str("}\n");
+    code
+}
+
+fn field_name(name: &str) -> String {
+    if NON_RAW_NAMES.contains(&name) {
+        format!("{}_", name)
+    } else if RUST_KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_per_signal() {
+        let module = crate::parse("module pump_station\nsignal start\nsignal type\nsignal try\nsignal crate\nsignal self\nsignal Self\ncoil motor").unwrap();
+        let code = generate_inputs_struct(&module);
+        assert!(code.contains("pub struct PumpStationInputs {"));
+        assert!(code.contains("    pub start: bool,"));
+        assert!(code.contains("    pub r#type: bool,"));
+        assert!(code.contains("map.insert(\"type\".to_string(), self.r#type);"));
+        assert!(code.contains("    pub r#try: bool,"));
+        assert!(code.contains("    pub crate_: bool,"));
+        assert!(code.contains("    pub self_: bool,"));
+        assert!(code.contains("    pub Self_: bool,"));
+        assert!(code.contains("map.insert(\"crate\".to_string(), self.crate_);"));
+        assert!(!code.contains("motor"));
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use crate::ast::Module;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::Result;
+use crate::{check_types, emit_ir, parse_named, parse_with_warnings, resolve_names};
+
+/// The outcome of compiling a source, for hosts embedding the compiler.
+///
+/// Compilation stops at the first error; everything produced up to that
+/// point is kept alongside every diagnostic raised.
+#[derive(Debug, Default)]
+pub struct Compilation {
+    pub module: Option<Module>,
+    pub ir: Option<String>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Compilation {
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+    
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Error)
+    }
+    
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Warning)
+    }
+}
+
+/// Parse, resolve, type check and emit a source, collecting diagnostics instead of failing
+pub fn compile_collecting(source: &str) -> Compilation {
+    let mut compilation = Compilation::default();
+    
+    let mut module = match parse_with_warnings(source) {
+        Ok((module, warnings)) => {
+            compilation.diagnostics.extend(warnings);
+            module
+        }
+        Err(e) => {
+            compilation.diagnostics.push(Diagnostic::from(&e));
+            return compilation;
+        }
+    };
+    
+    let resolved = resolve_names(&mut module);
+    match resolved {
+        Ok(warnings) => compilation.diagnostics.extend(warnings),
+        Err(e) => {
+            compilation.diagnostics.push(Diagnostic::from(&e));
+            compilation.module = Some(module);
+            return compilation;
+        }
+    }
+    
+    if let Err(e) = check_types(&module) {
+        compilation.diagnostics.push(Diagnostic::from(&e));
+        compilation.module = Some(module);
+        return compilation;
+    }
+    
+    match emit_ir(&module) {
+        Ok(ir) => compilation.ir = Some(ir),
+        Err(e) => compilation.diagnostics.push(Diagnostic::from(&e)),
+    }
+    compilation.module = Some(module);
+    compilation
+}
+
+/// Compile several `(path, source)` pairs, returning each path with its result.
+///
+/// As in batch CLI builds, a module name declared by an earlier source is
+/// reported as an error on every later source that declares it again.
+pub fn compile_many(sources: &[(String, String)]) -> Vec<(String, Compilation)> {
+    let mut declared: HashMap<String, &str> = HashMap::new();
+    let mut results = Vec::with_capacity(sources.len());
+    for (path, source) in sources {
+        let mut compilation = compile_collecting(source);
+        if let Some(name) = compilation.module.as_ref().map(|m| m.name.clone()) {
+            match declared.get(&name) {
+                Some(first) => compilation.diagnostics.push(Diagnostic::error(
+                    format!("module '{}' is declared in both {} and {}", name, first, path)
+                )),
+                None => {
+                    declared.insert(name, path);
+                }
+            }
+        }
+        results.push((path.clone(), compilation));
+    }
+    results
+}
+
+/// Compile a source to IR, recording `path` in any parse error
+pub fn compile_named(source: &str, path: &str) -> Result<String> {
+    let mut module = parse_named(source, path)?;
+    resolve_names(&mut module)?;
+    check_types(&module)?;
+    emit_ir(&module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collects_errors_and_warnings() {
+        let compilation = compile_collecting("module m\nsignal a\ncoil x\nrung r:\n  when NOT a AND missing\n  then energise x");
+        assert!(compilation.has_errors());
+        assert_eq!(compilation.warnings().count(), 1);
+        assert_eq!(compilation.errors().count(), 1);
+        assert!(compilation.errors().next().unwrap().message.contains("missing"));
+        assert!(compilation.module.is_some());
+        assert!(compilation.ir.is_none());
+        
+        let mistyped = compile_collecting("module m\nsignal a\nsignal b\ncoil x\nrung r:\n  when NOT a AND NO b\n  then energise x = 5");
+        assert_eq!(mistyped.warnings().count(), 1);
+        assert_eq!(mistyped.errors().count(), 1);
+        assert_eq!(mistyped.errors().next().unwrap().message, "Type error: Value of `energise x = 5` must be boolean");
+        assert!(mistyped.module.is_some());
+        assert!(mistyped.ir.is_none());
+        
+        let clean = compile_collecting("module m\nsignal a\ncoil x\nrung r:\n  when NO a\n  then energise x");
+        assert!(!clean.has_errors());
+        assert!(clean.ir.is_some());
+    }
+
+    #[test]
+    fn test_compile_many_reports_per_file() {
+        let sources = vec![
+            ("good.charta".to_string(), "module good\nsignal a\ncoil x\nrung r:\n  when NO a\n  then energise x".to_string()),
+            ("bad.charta".to_string(), "module bad\ncoil x\nrung r:\n  when NO missing\n  then energise x".to_string()),
+        ];
+        let results = compile_many(&sources);
+        assert_eq!(results.len(), 2);
+        
+        let (path, good) = &results[0];
+        assert_eq!(path, "good.charta");
+        assert!(!good.has_errors());
+        assert!(good.ir.is_some());
+        
+        let (path, bad) = &results[1];
+        assert_eq!(path, "bad.charta");
+        assert!(bad.has_errors());
+        assert!(bad.ir.is_none());
+        assert!(bad.errors().next().unwrap().message.contains("missing"));
+    }
+
+    #[test]
+    fn test_edge_contacts_on_non_boolean_signals_are_rejected() {
+        let source = "module m\nsignal level: real\ncoil x\nrung r:\n  when P level\n  then energise x";
+        let message = "Type error: Edge contact `P level` needs a boolean signal, but 'level' is declared as real";
+        
+        let compilation = compile_collecting(source);
+        assert_eq!(compilation.errors().next().unwrap().message, message);
+        assert!(compilation.ir.is_none());
+        
+        let err = compile_named(source, "m.charta").unwrap_err();
+        assert_eq!(err.to_string(), message);
+        assert!(compile_named(&source.replace(": real", ": bool"), "m.charta").is_ok());
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+
+use crate::error::CompileError;
+
+/// Non-fatal compiler diagnostics (warnings and advisories)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// Replacement text for an obvious fix, for editor quick fixes
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            line: None,
+            column: None,
+            suggestion: None,
+        }
+    }
+    
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            line: None,
+            column: None,
+            suggestion: None,
+        }
+    }
+    
+    pub fn note(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Note,
+            message: message.into(),
+            line: None,
+            column: None,
+            suggestion: None,
+        }
+    }
+    
+    pub fn at(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+    
+    pub fn with_suggestion(mut self, suggestion: Option<String>) -> Self {
+        self.suggestion = suggestion;
+        self
+    }
+}
+
+impl From<&CompileError> for Diagnostic {
+    fn from(error: &CompileError) -> Self {
+        let diagnostic = match error {
+            CompileError::Parse { line, column, message, .. } => Diagnostic::error(message.clone()).at(*line, *column),
+            CompileError::Emission { message, position: Some(position) } => {
+                Diagnostic::error(message.clone()).at(position.line, position.column)
+            }
+            other => Diagnostic::error(other.to_string()),
+        };
+        diagnostic.with_suggestion(error.suggestion().map(str::to_string))
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{} at line {}, column {}: {}", self.severity, line, column, self.message),
+            _ => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
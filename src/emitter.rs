@@ -1,21 +1,484 @@
 use crate::ast;
 use crate::error::{CompileError, Result};
+use crate::lower::lower_module;
+use crate::normalize::normalize_guard;
+use crate::resolver::{block_composition, coil_dependencies};
 use charta_core::ir::schema::{
     IR, Module as IRModule, Intent, Constraints, DataPrivacy, Quality, Cost,
     SignalDecl, CoilDecl, RungDecl, GuardExpr, Action, Expr,
     BlockDecl, PortDecl, NetworkDecl, Wire, Output,
 };
+use bincode::Options;
+use serde::{Deserialize, Serialize};
 use serde_json;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Optional sections to include in emitted IR, and how to lay it out
+#[derive(Debug, Clone)]
+pub struct EmitOptions {
+    /// Add a `metadata.dependencies` map from each coil to the signals that drive it
+    pub with_deps: bool,
+    /// Lower the module to primitive guards and actions before emitting
+    pub lower: bool,
+    /// Add a `guard_text` rendering of each rung's guard in source syntax
+    pub guard_text: bool,
+    /// Emit guards in normalized form, with commutative operands sorted
+    pub canonical_guards: bool,
+    /// Indent JSON output; compact output is a single line
+    pub pretty: bool,
+}
+
+impl Default for EmitOptions {
+    fn default() -> Self {
+        Self {
+            with_deps: false,
+            lower: false,
+            guard_text: false,
+            canonical_guards: false,
+            pretty: true,
+        }
+    }
+}
 
 /// Emit IR from AST
 pub fn emit_ir(module: &ast::Module) -> Result<String> {
+    emit_ir_with(module, &EmitOptions::default())
+}
+
+/// Emit IR from AST with explicit options
+pub fn emit_ir_with(module: &ast::Module, options: &EmitOptions) -> Result<String> {
+    let mut bytes = Vec::new();
+    emit_ir_with_to(module, options, &mut bytes)?;
+    String::from_utf8(bytes).map_err(|e| CompileError::emission(format!("IR is not valid UTF-8: {}", e)))
+}
+
+/// Emit IR from AST, writing it to `w`
+pub fn emit_ir_to<W: Write>(module: &ast::Module, w: W) -> Result<()> {
+    emit_ir_with_to(module, &EmitOptions::default(), w)
+}
+
+/// Emit IR from AST with explicit options, writing it to `w`.
+///
+/// Compiler extensions are patched into a JSON value before serialization,
+/// so the document is still built in memory; only the text is streamed.
+pub fn emit_ir_with_to<W: Write>(module: &ast::Module, options: &EmitOptions, w: W) -> Result<()> {
+    let ir = emit_ir_value(module, options)?;
+    if options.pretty {
+        serde_json::to_writer_pretty(w, &ir).map_err(json_error)
+    } else {
+        serde_json::to_writer(w, &ir).map_err(json_error)
+    }
+}
+
+/// Build the IR document, including compiler extensions, as a JSON value
+fn emit_ir_value(module: &ast::Module, options: &EmitOptions) -> Result<serde_json::Value> {
+    let lowered;
+    let module = if options.lower {
+        lowered = lower_module(module)?;
+        &lowered
+    } else {
+        module
+    };
+    let canonical;
+    let module = if options.canonical_guards {
+        canonical = canonicalize_guards(module);
+        &canonical
+    } else {
+        module
+    };
+    
     let ir = IR {
         version: "0.1.0".to_string(),
         module: emit_module(module)?,
     };
     
-    serde_json::to_string_pretty(&ir)
-        .map_err(|e| CompileError::Emission(format!("JSON serialization error: {}", e)))
+    let mut value = serde_json::to_value(&ir).map_err(json_error)?;
+    emit_extensions(module, &mut value)?;
+    if options.with_deps {
+        let dependencies = serde_json::to_value(coil_dependencies(module)).map_err(json_error)?;
+        value["module"]["metadata"] = serde_json::json!({ "dependencies": dependencies });
+    }
+    if options.guard_text {
+        if let Some(ir_rungs) = value["module"]["rungs"].as_array_mut() {
+            for (rung, ir_rung) in module.rungs.iter().zip(ir_rungs) {
+                ir_rung["guard_text"] = serde_json::Value::from(rung.guard.to_string());
+            }
+        }
+    }
+    Ok(value)
+}
+
+/// A copy of `module` with every rung guard and `require` condition normalized
+fn canonicalize_guards(module: &ast::Module) -> ast::Module {
+    let mut module = module.clone();
+    for rung in &mut module.rungs {
+        rung.guard = normalize_guard(&rung.guard);
+        for action in rung.actions.iter_mut().chain(rung.else_actions.iter_mut()) {
+            if let Some(condition) = &mut action.condition {
+                *condition = normalize_guard(condition);
+            }
+        }
+    }
+    module
+}
+
+/// An output format for compiled modules
+pub trait Emitter {
+    fn emit(&self, module: &ast::Module) -> Result<String>;
+    
+    /// Output as written to disk; text formats write their UTF-8 encoding
+    fn emit_bytes(&self, module: &ast::Module) -> Result<Vec<u8>> {
+        self.emit(module).map(String::into_bytes)
+    }
+    
+    /// Write the output to `w`; formats that can stream override this
+    fn emit_to(&self, module: &ast::Module, w: &mut dyn Write) -> Result<()> {
+        w.write_all(&self.emit_bytes(module)?)?;
+        Ok(())
+    }
+    
+    /// Whether the output is binary rather than text
+    fn is_binary(&self) -> bool {
+        false
+    }
+    
+    /// File extension for emitted output, without the leading dot
+    fn extension(&self) -> &str;
+}
+
+/// Pretty-printed JSON IR
+#[derive(Debug, Clone, Default)]
+pub struct JsonEmitter {
+    pub options: EmitOptions,
+}
+
+impl Emitter for JsonEmitter {
+    fn emit(&self, module: &ast::Module) -> Result<String> {
+        emit_ir_with(module, &self.options)
+    }
+    
+    fn emit_to(&self, module: &ast::Module, w: &mut dyn Write) -> Result<()> {
+        emit_ir_with_to(module, &self.options, w)
+    }
+    
+    fn extension(&self) -> &str {
+        "ir.json"
+    }
+}
+
+/// The same IR document as YAML
+#[derive(Debug, Clone, Default)]
+pub struct YamlEmitter {
+    pub options: EmitOptions,
+}
+
+impl Emitter for YamlEmitter {
+    fn emit(&self, module: &ast::Module) -> Result<String> {
+        serde_yaml::to_string(&emit_ir_value(module, &self.options)?)
+            .map_err(|e| CompileError::emission(format!("YAML serialization error: {}", e)))
+    }
+    
+    fn extension(&self) -> &str {
+        "ir.yaml"
+    }
+}
+
+/// Compact binary IR for embedded deployment, see `emit_ir_binary`
+#[derive(Debug, Clone, Default)]
+pub struct BincodeEmitter {
+    pub options: EmitOptions,
+}
+
+impl Emitter for BincodeEmitter {
+    fn emit(&self, _module: &ast::Module) -> Result<String> {
+        Err(CompileError::emission("bincode output is binary; use emit_bytes".to_string()))
+    }
+    
+    fn emit_bytes(&self, module: &ast::Module) -> Result<Vec<u8>> {
+        encode_binary(&emit_ir_value(module, &self.options)?)
+    }
+    
+    fn is_binary(&self) -> bool {
+        true
+    }
+    
+    fn extension(&self) -> &str {
+        "ir.bin"
+    }
+}
+
+/// Names accepted by `emitter_for`
+pub const FORMATS: &[&str] = &["json", "yaml", "bincode"];
+
+/// Emitters added by downstream crates through `register_emitter`
+type Registry = Mutex<HashMap<String, Arc<dyn Emitter + Send + Sync>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Make a custom backend selectable by `emitter_for` (and so `--format`) as `name`.
+///
+/// Built-in format names cannot be replaced; registering a name again
+/// replaces the earlier custom emitter.
+pub fn register_emitter(name: &str, emitter: Box<dyn Emitter + Send + Sync>) -> Result<()> {
+    if FORMATS.contains(&name) {
+        return Err(CompileError::emission(format!("Cannot replace built-in output format: {}", name)));
+    }
+    registry().lock().unwrap_or_else(|e| e.into_inner()).insert(name.to_string(), Arc::from(emitter));
+    Ok(())
+}
+
+/// A registered emitter, shared between every lookup of its name
+struct RegisteredEmitter(Arc<dyn Emitter + Send + Sync>);
+
+impl Emitter for RegisteredEmitter {
+    fn emit(&self, module: &ast::Module) -> Result<String> {
+        self.0.emit(module)
+    }
+    
+    fn emit_bytes(&self, module: &ast::Module) -> Result<Vec<u8>> {
+        self.0.emit_bytes(module)
+    }
+    
+    fn emit_to(&self, module: &ast::Module, w: &mut dyn Write) -> Result<()> {
+        self.0.emit_to(module, w)
+    }
+    
+    fn is_binary(&self) -> bool {
+        self.0.is_binary()
+    }
+    
+    fn extension(&self) -> &str {
+        self.0.extension()
+    }
+}
+
+/// Look up the emitter for a `--format` name, built-in or registered.
+///
+/// Registered emitters are used as given; `options` only apply to built-ins.
+pub fn emitter_for(format: &str, options: EmitOptions) -> Result<Box<dyn Emitter>> {
+    match format {
+        "json" => Ok(Box::new(JsonEmitter { options })),
+        "yaml" => Ok(Box::new(YamlEmitter { options })),
+        "bincode" => Ok(Box::new(BincodeEmitter { options })),
+        _ => {
+            let registry = registry().lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(emitter) = registry.get(format) {
+                return Ok(Box::new(RegisteredEmitter(Arc::clone(emitter))));
+            }
+            let mut known: Vec<&str> = FORMATS.to_vec();
+            let mut custom: Vec<&str> = registry.keys().map(String::as_str).collect();
+            custom.sort();
+            known.extend(custom);
+            Err(CompileError::emission(
+                format!("Unknown output format: {} (expected one of: {})", format, known.join(", "))
+            ))
+        }
+    }
+}
+
+/// Leading bytes identifying binary IR
+pub const BINARY_IR_MAGIC: &[u8] = b"CHIR";
+
+/// The IR document in a form bincode can decode.
+///
+/// bincode is not self-describing, so it cannot deserialize the tagged and
+/// untagged enums in the `charta_core` schema directly. Encoding the JSON
+/// document tree instead also keeps the compiler's IR extensions.
+#[derive(Debug, Serialize, Deserialize)]
+enum BinaryValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<BinaryValue>),
+    Object(Vec<(String, BinaryValue)>),
+}
+
+impl From<&serde_json::Value> for BinaryValue {
+    fn from(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => BinaryValue::Null,
+            serde_json::Value::Bool(b) => BinaryValue::Bool(*b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => BinaryValue::Int(i),
+                None => BinaryValue::Float(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(s) => BinaryValue::String(s.clone()),
+            serde_json::Value::Array(items) => BinaryValue::Array(items.iter().map(BinaryValue::from).collect()),
+            serde_json::Value::Object(fields) => BinaryValue::Object(
+                fields.iter().map(|(key, value)| (key.clone(), BinaryValue::from(value))).collect()
+            ),
+        }
+    }
+}
+
+impl From<BinaryValue> for serde_json::Value {
+    fn from(value: BinaryValue) -> Self {
+        match value {
+            BinaryValue::Null => serde_json::Value::Null,
+            BinaryValue::Bool(b) => serde_json::Value::Bool(b),
+            BinaryValue::Int(i) => serde_json::Value::from(i),
+            BinaryValue::Float(f) => serde_json::Value::from(f),
+            BinaryValue::String(s) => serde_json::Value::String(s),
+            BinaryValue::Array(items) => serde_json::Value::Array(items.into_iter().map(Into::into).collect()),
+            BinaryValue::Object(fields) => serde_json::Value::Object(
+                fields.into_iter().map(|(key, value)| (key, value.into())).collect()
+            ),
+        }
+    }
+}
+
+fn encode_binary(value: &serde_json::Value) -> Result<Vec<u8>> {
+    let mut bytes = BINARY_IR_MAGIC.to_vec();
+    bincode::DefaultOptions::new().serialize_into(&mut bytes, &BinaryValue::from(value))
+        .map_err(|e| CompileError::emission(format!("bincode serialization error: {}", e)))?;
+    Ok(bytes)
+}
+
+/// Emit IR from AST as varint-encoded bincode, prefixed with `BINARY_IR_MAGIC`
+pub fn emit_ir_binary(module: &ast::Module) -> Result<Vec<u8>> {
+    encode_binary(&emit_ir_value(module, &EmitOptions::default())?)
+}
+
+/// Decode binary IR back to the JSON text accepted by `load_ir`
+pub fn decode_ir_binary(bytes: &[u8]) -> Result<String> {
+    let payload = bytes.strip_prefix(BINARY_IR_MAGIC)
+        .ok_or_else(|| CompileError::emission("Not binary IR: missing header".to_string()))?;
+    let value: BinaryValue = bincode::DefaultOptions::new().deserialize(payload)
+        .map_err(|e| CompileError::emission(format!("Invalid binary IR: {}", e)))?;
+    serde_json::to_string(&serde_json::Value::from(value)).map_err(json_error)
+}
+
+fn json_error(e: serde_json::Error) -> CompileError {
+    CompileError::emission(format!("JSON serialization error: {}", e))
+}
+
+/// Add compiler-level fields that the `charta_core` schema types do not model
+fn emit_extensions(module: &ast::Module, ir: &mut serde_json::Value) -> Result<()> {
+    let ir_module = &mut ir["module"];
+    if module.is_library {
+        ir_module["library"] = serde_json::Value::Bool(true);
+    }
+    
+    emit_decl_field("unit", module.signals.iter().map(|s| &s.unit), &mut ir_module["signals"]);
+    emit_decl_field("unit", module.coils.iter().map(|c| &c.unit), &mut ir_module["coils"]);
+    emit_decl_field("deprecated", module.signals.iter().map(|s| &s.deprecated), &mut ir_module["signals"]);
+    emit_decl_field("deprecated", module.coils.iter().map(|c| &c.deprecated), &mut ir_module["coils"]);
+    emit_decl_field("implementation", module.blocks.iter().map(|b| &b.implementation), &mut ir_module["blocks"]);
+    emit_decl_field("deprecated", module.blocks.iter().map(|b| &b.deprecated), &mut ir_module["blocks"]);
+    
+    if let Some(ir_blocks) = ir_module["blocks"].as_array_mut() {
+        for (block, ir_block) in module.blocks.iter().zip(ir_blocks) {
+            let chain = block_composition(&module.blocks, block).map_err(|e| CompileError::Emission {
+                message: format!("cannot emit block '{}': {}", block.name, e),
+                position: block.position,
+            })?;
+            if chain.len() > 1 {
+                ir_block["composition"] = serde_json::Value::from(chain);
+            }
+        }
+    }
+    
+    if let Some(ir_rungs) = ir_module["rungs"].as_array_mut() {
+        for (rung, ir_rung) in module.rungs.iter().zip(ir_rungs) {
+            emit_contact_kinds(&rung.guard, &mut ir_rung["guard"]);
+            
+            if let Some(ir_actions) = ir_rung["actions"].as_array_mut() {
+                for (action, ir_action) in rung.actions.iter().zip(ir_actions) {
+                    emit_action_value(action, ir_action)?;
+                }
+            }
+            
+            if !rung.else_actions.is_empty() {
+                let mut else_actions = Vec::new();
+                for action in &rung.else_actions {
+                    let mut ir_action = serde_json::to_value(emit_action(action)).map_err(json_error)?;
+                    emit_action_value(action, &mut ir_action)?;
+                    else_actions.push(ir_action);
+                }
+                ir_rung["else_actions"] = serde_json::Value::Array(else_actions);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Add an optional string, such as a unit or block implementation, to the
+/// matching IR declarations
+fn emit_decl_field<'a>(key: &str, values: impl Iterator<Item = &'a Option<String>>, ir_decls: &mut serde_json::Value) {
+    if let Some(ir_decls) = ir_decls.as_array_mut() {
+        for (value, ir_decl) in values.zip(ir_decls) {
+            if let Some(value) = value {
+                ir_decl[key] = serde_json::Value::from(value.as_str());
+            }
+        }
+    }
+}
+
+/// Add the explicit `energise c = expr` output and `require` condition, which
+/// core IR actions cannot carry
+fn emit_action_value(action: &ast::Action, ir_action: &mut serde_json::Value) -> Result<()> {
+    if let Some(value) = &action.value {
+        ir_action["value"] = serde_json::to_value(emit_expr(value)).map_err(json_error)?;
+    }
+    if let Some(condition) = &action.condition {
+        ir_action["condition"] = serde_json::to_value(emit_guard(condition)?).map_err(json_error)?;
+        emit_contact_kinds(condition, &mut ir_action["condition"]);
+    }
+    Ok(())
+}
+
+/// Add the resolved `kind` of each contact in `guard` to its IR node
+fn emit_contact_kinds(guard: &ast::GuardExpr, ir_guard: &mut serde_json::Value) {
+    let mut kinds = Vec::new();
+    collect_contact_kinds(guard, &mut kinds);
+    let mut ir_contacts = Vec::new();
+    collect_ir_contacts(ir_guard, &mut ir_contacts);
+    for (kind, ir_contact) in kinds.into_iter().zip(ir_contacts) {
+        if let Some(kind) = kind {
+            ir_contact["kind"] = serde_json::Value::from(match kind {
+                ast::ContactKind::Signal => "signal",
+                ast::ContactKind::Coil => "coil",
+            });
+        }
+    }
+}
+
+/// Contact kinds in left-to-right source order
+fn collect_contact_kinds(guard: &ast::GuardExpr, kinds: &mut Vec<Option<ast::ContactKind>>) {
+    match guard {
+        ast::GuardExpr::Contact { kind, .. } => kinds.push(*kind),
+        ast::GuardExpr::And { left, right } | ast::GuardExpr::Or { left, right } => {
+            collect_contact_kinds(left, kinds);
+            collect_contact_kinds(right, kinds);
+        }
+        ast::GuardExpr::Not { expr } => collect_contact_kinds(expr, kinds),
+        ast::GuardExpr::Compare { .. } => {}
+    }
+}
+
+/// Serialized contact objects in the same left-to-right order as the AST
+fn collect_ir_contacts<'a>(value: &'a mut serde_json::Value, contacts: &mut Vec<&'a mut serde_json::Value>) {
+    match value {
+        serde_json::Value::Object(map) if map.contains_key("contact_type") => contacts.push(value),
+        serde_json::Value::Object(map) => {
+            for child in map.values_mut() {
+                collect_ir_contacts(child, contacts);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for child in items {
+                collect_ir_contacts(child, contacts);
+            }
+        }
+        _ => {}
+    }
 }
 
 fn emit_module(module: &ast::Module) -> Result<IRModule> {
@@ -89,13 +552,10 @@ fn emit_rung(rung: &ast::RungDecl) -> Result<RungDecl> {
 
 fn emit_guard(guard: &ast::GuardExpr) -> Result<GuardExpr> {
     match guard {
-        ast::GuardExpr::Contact { name, contact_type, arguments } => {
+        ast::GuardExpr::Contact { name, contact_type, arguments, .. } => {
             Ok(GuardExpr::Contact {
                 name: name.clone(),
-                contact_type: match contact_type {
-                    ast::ContactType::NO => "NO".to_string(),
-                    ast::ContactType::NC => "NC".to_string(),
-                },
+                contact_type: contact_type.symbol().to_string(),
                 arguments: Some(arguments.iter().map(emit_expr).collect()),
             })
         }
@@ -116,6 +576,12 @@ fn emit_guard(guard: &ast::GuardExpr) -> Result<GuardExpr> {
                 expr: Box::new(emit_guard(expr)?),
             })
         }
+        ast::GuardExpr::Compare { .. } => {
+            Err(CompileError::emission(format!(
+                "Comparison `{}` is not representable in IR v0.1",
+                guard
+            )))
+        }
     }
 }
 
@@ -130,12 +596,7 @@ fn emit_expr(expr: &ast::Expr) -> Expr {
 
 fn emit_action(action: &ast::Action) -> Action {
     Action {
-        action_type: match action.action_type {
-            ast::ActionType::Energise => "energise".to_string(),
-            ast::ActionType::DeEnergise => "de_energise".to_string(),
-            ast::ActionType::Escalate => "escalate".to_string(),
-            ast::ActionType::Require => "require".to_string(),
-        },
+        action_type: action.action_type.ir_name().to_string(),
         coil: action.coil.clone(),
         arguments: if action.arguments.is_empty() {
             None
@@ -183,6 +644,7 @@ mod tests {
     fn test_emit_basic_ir() {
         let module = ast::Module {
             name: "test".to_string(),
+            is_library: false,
             context: None,
             intent: None,
             constraints: None,
@@ -190,12 +652,18 @@ mod tests {
                 name: "input".to_string(),
                 parameters: Vec::new(),
                 type_: None,
+                unit: None,
+                feature: None,
+                deprecated: None,
             }],
             coils: vec![ast::CoilDecl {
                 name: "output".to_string(),
                 parameters: Vec::new(),
                 latching: None,
                 critical: None,
+                unit: None,
+                feature: None,
+                deprecated: None,
             }],
             rungs: vec![ast::RungDecl {
                 name: "r1".to_string(),
@@ -203,12 +671,17 @@ mod tests {
                     name: "input".to_string(),
                     contact_type: ast::ContactType::NO,
                     arguments: Vec::new(),
+                    kind: None,
                 },
                 actions: vec![ast::Action {
                     action_type: ast::ActionType::Energise,
                     coil: "output".to_string(),
                     arguments: Vec::new(),
+                    value: None,
+                    condition: None,
                 }],
+                else_actions: Vec::new(),
+                feature: None,
             }],
             blocks: Vec::new(),
             networks: Vec::new(),
@@ -218,5 +691,278 @@ mod tests {
         assert!(ir_json.contains("test"));
         assert!(ir_json.contains("input"));
         assert!(ir_json.contains("output"));
+        assert!(!ir_json.contains("library"));
+    }
+
+    #[test]
+    fn test_emit_contact_kind() {
+        let source = r#"
+module kinds
+
+signal start
+coil running
+coil lamp
+
+rung hold:
+  when NO start OR NO running
+  then energise running
+
+rung show:
+  when NO running
+  then energise lamp
+"#;
+        let mut module = crate::parse(source).unwrap();
+        crate::resolve_names(&mut module).unwrap();
+        let ir_json = emit_ir(&module).unwrap();
+        let ir: serde_json::Value = serde_json::from_str(&ir_json).unwrap();
+        
+        let mut contacts = Vec::new();
+        let mut show_guard = ir["module"]["rungs"][1]["guard"].clone();
+        collect_ir_contacts(&mut show_guard, &mut contacts);
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0]["kind"], "coil");
+        assert!(ir_json.contains("\"kind\": \"signal\""));
+    }
+
+    #[test]
+    fn test_emit_dependency_metadata() {
+        let source = r#"
+module deps
+signal start
+signal permit
+signal unrelated
+coil run
+rung r1:
+  when NO start AND NC permit
+  then energise run
+"#;
+        let module = crate::parse(source).unwrap();
+        let plain: serde_json::Value = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        assert!(plain["module"].get("metadata").is_none());
+        
+        let options = EmitOptions { with_deps: true, ..Default::default() };
+        let ir: serde_json::Value = serde_json::from_str(&emit_ir_with(&module, &options).unwrap()).unwrap();
+        assert_eq!(ir["module"]["metadata"]["dependencies"]["run"], serde_json::json!(["permit", "start"]));
+    }
+
+    #[test]
+    fn test_each_registered_emitter_produces_output() {
+        let module = crate::parse("module formats\nsignal a\ncoil b\nrung r:\n  when NO a\n  then energise b").unwrap();
+        for format in FORMATS {
+            let emitter = emitter_for(format, EmitOptions::default()).unwrap();
+            let output = emitter.emit_bytes(&module).unwrap();
+            assert!(output.windows(7).any(|w| w == b"formats"), "{} output is missing the module", format);
+        }
+        assert!(emitter_for("xml", EmitOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_registered_emitter_is_selectable() {
+        struct SignalList;
+        impl Emitter for SignalList {
+            fn emit(&self, module: &ast::Module) -> Result<String> {
+                Ok(module.signals.iter().map(|s| format!("{}\n", s.name)).collect())
+            }
+            
+            fn extension(&self) -> &str {
+                "signals.txt"
+            }
+        }
+        
+        assert!(emitter_for("signal-list", EmitOptions::default()).is_err());
+        register_emitter("signal-list", Box::new(SignalList)).unwrap();
+        let emitter = emitter_for("signal-list", EmitOptions::default()).unwrap();
+        let module = crate::parse("library module m\nsignal a\nsignal b").unwrap();
+        assert_eq!(emitter.emit(&module).unwrap(), "a\nb\n");
+        assert_eq!(emitter.extension(), "signals.txt");
+        
+        assert!(register_emitter("json", Box::new(SignalList)).is_err());
+    }
+
+    #[test]
+    fn test_emit_library_flag() {
+        let module = crate::parse("library module utils\nsignal shared_input").unwrap();
+        let ir: serde_json::Value = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        assert_eq!(ir["module"]["library"], true);
+    }
+
+    #[test]
+    fn test_emit_explicit_energise_value() {
+        let module = crate::parse("module m\nsignal a\ncoil flag\ncoil lamp\nrung r:\n  when NO a\n  then energise flag = false energise lamp").unwrap();
+        let ir: serde_json::Value = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        let actions = &ir["module"]["rungs"][0]["actions"];
+        assert_eq!(actions[0]["value"], serde_json::to_value(Expr::Boolean(false)).unwrap());
+        assert!(actions[1].get("value").is_none());
+    }
+
+    #[test]
+    fn test_emit_negative_number_argument() {
+        let module = crate::parse("module m\nsignal a\ncoil setpoint(offset)\nrung r:\n  when NO a\n  then energise setpoint(-2.5)").unwrap();
+        let ir: IR = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        let rungs = ir.module.rungs.unwrap();
+        assert!(matches!(rungs[0].actions[0].arguments.as_deref(), Some([Expr::Number(n)]) if *n == -2.5));
+    }
+
+    #[test]
+    fn test_emit_block_composition() {
+        let mut module = crate::parse("library module m\nabstract block outer:\nabstract block inner:").unwrap();
+        module.blocks[0].implementation = Some("inner".to_string());
+        let ir: serde_json::Value = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        assert_eq!(ir["module"]["blocks"][0]["composition"], serde_json::json!(["outer", "inner"]));
+        assert!(ir["module"]["blocks"][1].get("composition").is_none());
+    }
+
+    #[test]
+    fn test_emit_require_condition() {
+        let module = crate::parse("module m\nrung r:\n  when NO start\n  then require NO door_closed").unwrap();
+        let ir: serde_json::Value = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        let action = &ir["module"]["rungs"][0]["actions"][0];
+        assert_eq!(action["action_type"], "require");
+        assert_eq!(action["condition"]["name"], "door_closed");
+        
+        let mut module = crate::parse("module m\nsignal start\nsignal door_closed\ncoil x\nrung r:\n  when NO start\n  then require NO door_closed, energise x").unwrap();
+        crate::resolve_names(&mut module).unwrap();
+        let ir: serde_json::Value = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        assert_eq!(ir["module"]["rungs"][0]["actions"][0]["condition"]["kind"], "signal");
+    }
+
+    #[test]
+    fn test_emit_lowered_else() {
+        let module = crate::parse("module m\nrung r:\n  when NO a\n  then energise x\n  else de_energise x").unwrap();
+        let options = EmitOptions { lower: true, ..Default::default() };
+        let ir: serde_json::Value = serde_json::from_str(&emit_ir_with(&module, &options).unwrap()).unwrap();
+        let rungs = ir["module"]["rungs"].as_array().unwrap();
+        assert_eq!(rungs.len(), 2);
+        assert!(rungs.iter().all(|rung| rung.get("else_actions").is_none()));
+        assert_eq!(rungs[1]["actions"][0]["action_type"], "de_energise");
+    }
+
+    #[test]
+    fn test_block_emission_error_reports_block_line() {
+        let mut module = crate::parse("library module m\nsignal s\nabstract block first:\n\n  abstract block second:").unwrap();
+        module.blocks[0].implementation = Some("second".to_string());
+        module.blocks[1].implementation = Some("first".to_string());
+        match emit_ir(&module) {
+            Err(error @ CompileError::Emission { position: Some(position), .. }) => {
+                assert_eq!((position.line, position.column), (3, 1));
+                assert!(error.to_string().starts_with("IR emission error at line 3, column 1: cannot emit block 'first'"), "{}", error);
+            }
+            other => panic!("expected a positioned emission error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_emit_units() {
+        let module = crate::parse("library module m\nsignal temp: real unit: \"degC\"\nsignal flag\ncoil valve [critical] unit: \"bar\"").unwrap();
+        let ir: serde_json::Value = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        assert_eq!(ir["module"]["signals"][0]["unit"], "degC");
+        assert!(ir["module"]["signals"][1].get("unit").is_none());
+        assert_eq!(ir["module"]["coils"][0]["unit"], "bar");
+        assert_eq!(ir["module"]["coils"][0]["critical"], true);
+    }
+
+    #[test]
+    fn test_emit_block_implementation() {
+        let module = crate::parse("library module m\nblock score: { implementation: \"models.risk:score\" }\nabstract block stub:").unwrap();
+        let ir: serde_json::Value = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        assert_eq!(ir["module"]["blocks"][0]["implementation"], "models.risk:score");
+        assert!(ir["module"]["blocks"][1].get("implementation").is_none());
+    }
+
+    #[test]
+    fn test_emit_edge_contacts() {
+        let module = crate::parse("module m\nsignal start\nsignal stop\ncoil run\nrung r:\n  when P start OR N stop\n  then energise run").unwrap();
+        let ir: IR = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        let rungs = ir.module.rungs.unwrap();
+        match &rungs[0].guard {
+            GuardExpr::Or { left, right } => {
+                assert!(matches!(left.as_ref(), GuardExpr::Contact { contact_type, .. } if contact_type == "P"));
+                assert!(matches!(right.as_ref(), GuardExpr::Contact { contact_type, .. } if contact_type == "N"));
+            }
+            other => panic!("expected OR, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_emit_comparison_guard_is_rejected() {
+        let module = crate::parse("module m\nsignal start\nsignal temperature\ncoil fan\nrung r:\n  when NO start AND temperature > 80\n  then energise fan").unwrap();
+        let err = emit_ir(&module).unwrap_err();
+        assert_eq!(err.to_string(), "IR emission error: Comparison `temperature > 80` is not representable in IR v0.1");
+    }
+
+    #[test]
+    fn test_emit_deprecated() {
+        let module = crate::parse("library module m\n@deprecated(\"use b\")\nsignal a\nsignal b\n@deprecated\ncoil c").unwrap();
+        let ir: serde_json::Value = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        assert_eq!(ir["module"]["signals"][0]["deprecated"], "use b");
+        assert!(ir["module"]["signals"][1].get("deprecated").is_none());
+        assert_eq!(ir["module"]["coils"][0]["deprecated"], "");
+    }
+
+    #[test]
+    fn test_compact_ir_is_one_stable_line() {
+        let module = crate::parse("module m\nsignal start\ncoil motor\nrung r:\n  when NO start\n  then energise motor").unwrap();
+        let options = EmitOptions { pretty: false, ..Default::default() };
+        let compact = emit_ir_with(&module, &options).unwrap();
+        assert!(!compact.contains('\n') && !compact.contains(": "));
+        assert_eq!(compact, emit_ir_with(&module, &options).unwrap());
+        
+        let pretty: serde_json::Value = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&compact).unwrap(), pretty);
+    }
+
+    #[test]
+    fn test_emit_ir_to_writes_the_same_document() {
+        let module = crate::parse("module m\nsignal start\ncoil motor\nrung r:\n  when NO start\n  then energise motor").unwrap();
+        let mut written = Vec::new();
+        emit_ir_to(&module, &mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), emit_ir(&module).unwrap());
+        
+        let mut streamed = Vec::new();
+        emitter_for("yaml", EmitOptions::default()).unwrap().emit_to(&module, &mut streamed).unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), YamlEmitter::default().emit(&module).unwrap());
+    }
+
+    #[test]
+    fn test_binary_ir_round_trips() {
+        let mut module = crate::parse("module m\nsignal start\ncoil motor\nrung r:\n  when NO start\n  then energise motor").unwrap();
+        crate::resolve_names(&mut module).unwrap();
+        let json = emit_ir(&module).unwrap();
+        let binary = emit_ir_binary(&module).unwrap();
+        assert!(binary.len() < json.len());
+        
+        let decoded = decode_ir_binary(&binary).unwrap();
+        let from_binary: IR = serde_json::from_str(&decoded).unwrap();
+        let from_json: IR = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_value(from_binary).unwrap(), serde_json::to_value(from_json).unwrap());
+        
+        assert!(decode_ir_binary(json.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_canonical_guards_ignore_operand_order() {
+        let options = EmitOptions { canonical_guards: true, ..Default::default() };
+        let emit = |guard: &str| {
+            let source = format!("module m\nsignal a\nsignal b\ncoil c\nrung r:\n  when {}\n  then energise c", guard);
+            let mut module = crate::parse(&source).unwrap();
+            crate::resolve_names(&mut module).unwrap();
+            (emit_ir(&module).unwrap(), emit_ir_with(&module, &options).unwrap())
+        };
+        let (plain_ab, canonical_ab) = emit("NO a AND NC b");
+        let (plain_ba, canonical_ba) = emit("NC b AND NO a");
+        assert_ne!(plain_ab, plain_ba);
+        assert_eq!(canonical_ab, canonical_ba);
+    }
+
+    #[test]
+    fn test_guard_text() {
+        let module = crate::parse("module m\nsignal a\nsignal b\ncoil x\nrung r:\n  when NO a AND NC b\n  then energise x").unwrap();
+        let plain: serde_json::Value = serde_json::from_str(&emit_ir(&module).unwrap()).unwrap();
+        assert!(plain["module"]["rungs"][0].get("guard_text").is_none());
+        
+        let options = EmitOptions { guard_text: true, ..Default::default() };
+        let ir: serde_json::Value = serde_json::from_str(&emit_ir_with(&module, &options).unwrap()).unwrap();
+        assert_eq!(ir["module"]["rungs"][0]["guard_text"], "NO a AND NC b");
+        assert_eq!(ir["module"]["rungs"][0]["guard"]["type"], "And");
     }
 }
@@ -1,25 +1,95 @@
 use thiserror::Error;
 
+use crate::ast::Position;
+
 pub type Result<T> = std::result::Result<T, CompileError>;
 
 #[derive(Error, Debug)]
 pub enum CompileError {
-    #[error("Parse error at line {line}, column {column}: {message}")]
+    #[error("Parse error {}at line {line}, column {column}: {message}", in_file(.file))]
     Parse {
         line: usize,
         column: usize,
         message: String,
+        /// Source path, when the caller named one
+        file: Option<String>,
+        /// Replacement text for an obvious fix
+        suggestion: Option<String>,
     },
     
-    #[error("Name resolution error: {0}")]
-    NameResolution(String),
+    #[error("Name resolution error: {message}{}", did_you_mean(.suggestion))]
+    NameResolution {
+        message: String,
+        /// Replacement text for an obvious fix, such as the closest declared name
+        suggestion: Option<String>,
+    },
     
     #[error("Type error: {0}")]
     Type(String),
     
-    #[error("IR emission error: {0}")]
-    Emission(String),
+    #[error("IR emission error{}: {message}", at(.position))]
+    Emission {
+        message: String,
+        /// Source position of the construct that could not be emitted
+        position: Option<Position>,
+    },
+    
+    #[error("aborting due to {0} previous error(s)")]
+    Aborted(usize),
     
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
+
+impl CompileError {
+    /// An emission error with no source position
+    pub fn emission(message: impl Into<String>) -> Self {
+        CompileError::Emission {
+            message: message.into(),
+            position: None,
+        }
+    }
+    
+    /// A name resolution error with no suggested fix
+    pub fn name_resolution(message: impl Into<String>) -> Self {
+        CompileError::NameResolution {
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+    
+    /// Attach a source path to a parse error; other errors are returned unchanged
+    pub fn with_file(self, path: &str) -> Self {
+        match self {
+            CompileError::Parse { line, column, message, suggestion, .. } => CompileError::Parse {
+                line,
+                column,
+                message,
+                file: Some(path.to_string()),
+                suggestion,
+            },
+            other => other,
+        }
+    }
+    
+    /// Suggested fix carried by parse and name resolution errors
+    pub fn suggestion(&self) -> Option<&str> {
+        match self {
+            CompileError::Parse { suggestion, .. } | CompileError::NameResolution { suggestion, .. } => suggestion.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// ` at line L, column C` for a known position, otherwise empty
+pub(crate) fn at(position: &Option<Position>) -> String {
+    position.map(|p| format!(" at line {}, column {}", p.line, p.column)).unwrap_or_default()
+}
+
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    suggestion.as_ref().map(|s| format!("; did you mean `{}`?", s)).unwrap_or_default()
+}
+
+fn in_file(file: &Option<String>) -> String {
+    file.as_ref().map(|f| format!("in {} ", f)).unwrap_or_default()
+}
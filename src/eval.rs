@@ -0,0 +1,123 @@
+use crate::ast::{ContactType, GuardExpr};
+use std::collections::HashMap;
+
+/// Evaluate a guard against a boolean assignment of signals and coils.
+///
+/// Names missing from `state` read as false, as an unset input does on the
+/// VM. An NO contact passes when its name is true and an NC contact when it
+/// is false; contact arguments are ignored. Comparisons and edge contacts,
+/// which depend on more than the current scan's booleans, are opaque atoms
+/// looked up by their source text, e.g. `"level > 80"` or `"P start"`.
+pub fn eval_guard(guard: &GuardExpr, state: &HashMap<String, bool>) -> bool {
+    match guard {
+        GuardExpr::Contact { name, contact_type, .. } => {
+            let value = state.get(name).copied().unwrap_or(false);
+            match contact_type {
+                ContactType::NO => value,
+                ContactType::NC => !value,
+                ContactType::RisingEdge | ContactType::FallingEdge => state.get(&guard.to_string()).copied().unwrap_or(false),
+            }
+        }
+        GuardExpr::And { left, right } => eval_guard(left, state) && eval_guard(right, state),
+        GuardExpr::Or { left, right } => eval_guard(left, state) || eval_guard(right, state),
+        GuardExpr::Not { expr } => !eval_guard(expr, state),
+        GuardExpr::Compare { .. } => state.get(&guard.to_string()).copied().unwrap_or(false),
+    }
+}
+
+/// Contact names beyond which `is_contradiction` stops enumerating assignments
+pub const MAX_CONTRADICTION_NAMES: usize = 16;
+
+/// Whether no assignment of its contacts makes `guard` hold.
+///
+/// Checked exhaustively, so guards reading more than
+/// `MAX_CONTRADICTION_NAMES` distinct names are reported as satisfiable.
+pub fn is_contradiction(guard: &GuardExpr) -> bool {
+    let mut names = Vec::new();
+    collect_names(guard, &mut names);
+    names.sort();
+    names.dedup();
+    if names.len() > MAX_CONTRADICTION_NAMES {
+        return false;
+    }
+    
+    (0..1u32 << names.len()).all(|bits| {
+        let state = names.iter().enumerate()
+            .map(|(i, name)| (name.clone(), bits & (1 << i) != 0))
+            .collect();
+        !eval_guard(guard, &state)
+    })
+}
+
+/// Whether every assignment of its contacts makes `guard` hold
+pub fn is_tautology(guard: &GuardExpr) -> bool {
+    is_contradiction(&GuardExpr::Not {
+        expr: Box::new(guard.clone()),
+    })
+}
+
+/// Names of the atoms `eval_guard` looks up: contact names, and the texts of
+/// comparisons and edge contacts
+fn collect_names(guard: &GuardExpr, names: &mut Vec<String>) {
+    match guard {
+        GuardExpr::Contact { contact_type, .. } if contact_type.is_edge() => names.push(guard.to_string()),
+        GuardExpr::Contact { name, .. } => names.push(name.clone()),
+        GuardExpr::And { left, right } | GuardExpr::Or { left, right } => {
+            collect_names(left, names);
+            collect_names(right, names);
+        }
+        GuardExpr::Not { expr } => collect_names(expr, names),
+        GuardExpr::Compare { .. } => names.push(guard.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(text: &str) -> GuardExpr {
+        let module = crate::parse(&format!("module m\nrung r:\n  when {}\n  then energise x", text)).unwrap();
+        module.rungs[0].guard.clone()
+    }
+
+    fn state(pairs: &[(&str, bool)]) -> HashMap<String, bool> {
+        pairs.iter().map(|(name, value)| (name.to_string(), *value)).collect()
+    }
+
+    #[test]
+    fn test_contacts() {
+        assert!(eval_guard(&guard("NO a"), &state(&[("a", true)])));
+        assert!(!eval_guard(&guard("NO a"), &state(&[("a", false)])));
+        assert!(!eval_guard(&guard("NC a"), &state(&[("a", true)])));
+        assert!(eval_guard(&guard("NC a"), &state(&[("a", false)])));
+        // An unset name reads false, so an NC contact on it passes
+        assert!(eval_guard(&guard("NC a"), &HashMap::new()));
+    }
+
+    #[test]
+    fn test_operators() {
+        let cases = [
+            ("NO a AND NO b", [false, false, false, true]),
+            ("NO a OR NO b", [false, true, true, true]),
+            ("NOT (NO a AND NO b)", [true, true, true, false]),
+            ("NO a AND NC b", [false, false, true, false]),
+        ];
+        for (text, expected) in cases {
+            let guard = guard(text);
+            for (i, want) in expected.into_iter().enumerate() {
+                let state = state(&[("a", i & 2 != 0), ("b", i & 1 != 0)]);
+                assert_eq!(eval_guard(&guard, &state), want, "{} with {:?}", text, state);
+            }
+        }
+    }
+
+    #[test]
+    fn test_contradiction() {
+        assert!(is_contradiction(&guard("NO a AND NOT NO a")));
+        assert!(is_contradiction(&guard("NO a AND NC a")));
+        assert!(!is_contradiction(&guard("NO a OR NC a")));
+        assert!(!is_contradiction(&guard("NO a AND NC b")));
+        assert!(is_tautology(&guard("NO a OR NC a")));
+        assert!(!is_tautology(&guard("NO a OR NC b")));
+    }
+}
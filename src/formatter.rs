@@ -0,0 +1,374 @@
+use crate::error::Result;
+use crate::parser::{group_breaks, tokenize, Token};
+use std::collections::HashSet;
+
+/// Format Charta source canonically.
+///
+/// Line breaks are kept, except that each declaration and each `when`,
+/// `then`, `else` and `end` of a rung starts a new line. Indentation and
+/// spacing are normalized and trailing whitespace is removed. Blank lines
+/// are kept only between declaration groups, one per gap, and every rung,
+/// block and network is set apart by blank lines. Comments are kept where they are. Formatting
+/// already formatted source returns it unchanged.
+pub fn format(source: &str) -> Result<String> {
+    let lines = scan(source)?;
+    let indents = indents(&lines);
+    let breaks = blank_lines(&lines, source);
+
+    let mut out = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if line.pieces.is_empty() {
+            continue;
+        }
+        if breaks[i] && !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&" ".repeat(indents[i]));
+        out.push_str(&render(&line.pieces));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+enum Piece<'a> {
+    Token(Token, &'a str),
+    Comment(&'a str),
+}
+
+/// A source line after forced breaks; blank when it has no pieces
+#[derive(Default)]
+struct Line<'a> {
+    pieces: Vec<Piece<'a>>,
+    /// Column of the first piece in the source, counted in characters
+    source_indent: usize,
+    /// Source line of the first token
+    source_line: usize,
+}
+
+impl Line<'_> {
+    fn first_token(&self) -> Option<&Token> {
+        self.pieces.iter().find_map(|piece| match piece {
+            Piece::Token(token, _) => Some(token),
+            Piece::Comment(_) => None,
+        })
+    }
+}
+
+/// Tokens that begin a top-level declaration or attribute
+fn is_starter(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Module | Token::Library | Token::Signal | Token::Coil | Token::Rung | Token::Block
+            | Token::Abstract | Token::Network | Token::Context | Token::Intent | Token::Constraints
+            | Token::AttributeStart | Token::At
+    )
+}
+
+fn is_rung_clause(token: &Token) -> bool {
+    matches!(token, Token::When | Token::Then | Token::Else | Token::End)
+}
+
+/// Declarations that get a blank line on either side
+fn is_sectioned(token: &Token) -> bool {
+    matches!(token, Token::Rung | Token::Block | Token::Abstract | Token::Network)
+}
+
+/// Brace depth and rung membership while walking tokens in order
+#[derive(Default)]
+struct Nesting {
+    depth: usize,
+    in_rung: bool,
+}
+
+impl Nesting {
+    fn advance(&mut self, token: &Token) {
+        match token {
+            Token::LBrace => self.depth += 1,
+            Token::RBrace => self.depth = self.depth.saturating_sub(1),
+            token if self.depth == 0 && is_starter(token) => self.in_rung = *token == Token::Rung,
+            _ => {}
+        }
+    }
+}
+
+/// Splits source into lines of tokens and comments
+struct Scanner<'a> {
+    source: &'a str,
+    lines: Vec<Line<'a>>,
+    /// Byte offset where the current source line starts
+    line_start: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn push(&mut self, start: usize, piece: Piece<'a>) {
+        let line = self.lines.last_mut().expect("there is always a current line");
+        if line.pieces.is_empty() {
+            line.source_indent = self.source[self.line_start..start].chars().count();
+        }
+        line.pieces.push(piece);
+    }
+    
+    fn new_line(&mut self, line_start: usize) {
+        self.lines.push(Line::default());
+        self.line_start = line_start;
+    }
+    
+    /// Take the comments and line breaks from the whitespace between tokens
+    fn gap(&mut self, start: usize, end: usize) {
+        let mut i = start;
+        while i < end {
+            let rest = &self.source[i..end];
+            if rest.starts_with('\n') {
+                self.new_line(i + 1);
+                i += 1;
+            } else if rest.starts_with("//") {
+                let len = rest.find('\n').unwrap_or(rest.len());
+                self.push(i, Piece::Comment(rest[..len].trim_end()));
+                i += len;
+            } else if rest.starts_with("/*") {
+                let len = rest.find("*/").map_or(rest.len(), |close| close + 2);
+                self.push(i, Piece::Comment(&rest[..len]));
+                if let Some(newline) = rest[..len].rfind('\n') {
+                    self.line_start = i + newline + 1;
+                }
+                i += len;
+            } else {
+                i += rest.chars().next().map_or(1, char::len_utf8);
+            }
+        }
+    }
+}
+
+/// Split source into lines of tokens and comments, breaking before every
+/// declaration and rung clause that shares a line with earlier code
+fn scan(source: &str) -> Result<Vec<Line<'_>>> {
+    let mut scanner = Scanner { source, lines: vec![Line::default()], line_start: 0 };
+    let mut nesting = Nesting::default();
+    let mut previous: Option<Token> = None;
+    let mut pos = 0;
+    for (token, span) in tokenize(source)? {
+        scanner.gap(pos, span.start);
+        let breaks = nesting.depth == 0
+            && (is_starter(&token) && !matches!(previous, Some(Token::Abstract | Token::Library))
+                || nesting.in_rung && is_rung_clause(&token));
+        if breaks && scanner.lines.last().is_some_and(|line| !line.pieces.is_empty()) {
+            scanner.new_line(span.start);
+        }
+        nesting.advance(&token);
+        if let Some(line) = scanner.lines.last_mut().filter(|line| line.first_token().is_none()) {
+            line.source_line = span.line;
+        }
+        scanner.push(span.start, Piece::Token(token.clone(), &source[span.start..span.end]));
+        previous = Some(token);
+        pos = span.end;
+    }
+    scanner.gap(pos, source.len());
+    Ok(scanner.lines)
+}
+
+/// Indentation of each line; comment-only lines take that of the next code line
+fn indents(lines: &[Line]) -> Vec<usize> {
+    let mut indents = vec![None; lines.len()];
+    let mut nesting = Nesting::default();
+    // Source indentation of nested lines, such as constraint sections, mapped to canonical levels
+    let mut levels: Vec<(usize, usize)> = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let Some(first) = line.first_token() else {
+            continue;
+        };
+        let depth = nesting.depth;
+        let starts_declaration = depth == 0 && is_starter(first);
+        for piece in &line.pieces {
+            if let Piece::Token(token, _) = piece {
+                nesting.advance(token);
+            }
+        }
+
+        indents[i] = Some(if starts_declaration {
+            levels.clear();
+            0
+        } else if depth > 0 {
+            match first {
+                Token::RBrace => 2 * (depth - 1),
+                Token::Inputs | Token::Outputs | Token::Internals | Token::Implementation
+                    | Token::Effect | Token::Wires => 2 * depth,
+                _ => 2 * depth + 2,
+            }
+        } else if nesting.in_rung {
+            if is_rung_clause(first) { 2 } else { 4 }
+        } else {
+            while levels.last().is_some_and(|&(source, _)| source > line.source_indent) {
+                levels.pop();
+            }
+            match levels.last() {
+                Some(&(source, level)) if source == line.source_indent => level,
+                last => {
+                    let level = last.map_or(2, |&(_, level)| level + 2);
+                    levels.push((line.source_indent, level));
+                    level
+                }
+            }
+        });
+    }
+
+    let mut filled = vec![0; lines.len()];
+    let mut next = 0;
+    for i in (0..lines.len()).rev() {
+        next = indents[i].unwrap_or(next);
+        filled[i] = next;
+    }
+    filled
+}
+
+/// What a line holds, for deciding where blank lines go
+#[derive(Clone, Copy, PartialEq)]
+enum Kind<'a> {
+    Blank,
+    Comment,
+    Attribute,
+    Declaration(&'a Token),
+    Body,
+}
+
+/// Whether each line is preceded by a blank line.
+///
+/// Declaration groups that `parser::group_breaks` finds in the source stay
+/// apart, and every rung, block and network is separated from the
+/// declarations around it. Comments and attributes directly above a
+/// declaration move with it.
+fn blank_lines(lines: &[Line], source: &str) -> Vec<bool> {
+    let source_breaks: HashSet<usize> = group_breaks(source).into_iter().collect();
+    let mut nesting = Nesting::default();
+    let kinds: Vec<Kind> = lines.iter()
+        .map(|line| {
+            let Some(first) = line.first_token() else {
+                return if line.pieces.is_empty() { Kind::Blank } else { Kind::Comment };
+            };
+            let kind = match first {
+                Token::AttributeStart | Token::At if nesting.depth == 0 => Kind::Attribute,
+                token if nesting.depth == 0 && is_starter(token) => Kind::Declaration(token),
+                _ => Kind::Body,
+            };
+            for piece in &line.pieces {
+                if let Piece::Token(token, _) = piece {
+                    nesting.advance(token);
+                }
+            }
+            kind
+        })
+        .collect();
+    
+    let mut breaks = vec![false; lines.len()];
+    let mut previous: Option<&Token> = None;
+    for (i, kind) in kinds.iter().enumerate() {
+        if !matches!(kind, Kind::Declaration(_) | Kind::Attribute) {
+            continue;
+        }
+        let mut start = i;
+        while start > 0 && matches!(kinds[start - 1], Kind::Comment | Kind::Attribute) {
+            start -= 1;
+        }
+        if source_breaks.contains(&lines[i].source_line) {
+            breaks[start] = true;
+        }
+        if let Kind::Declaration(token) = kind {
+            if previous.is_some_and(|previous| is_sectioned(previous) || is_sectioned(token)) {
+                breaks[start] = true;
+            }
+            previous = Some(token);
+        }
+    }
+    breaks
+}
+
+/// Render a line's pieces with canonical spacing
+fn render(pieces: &[Piece]) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&Token> = None;
+    for (i, piece) in pieces.iter().enumerate() {
+        match piece {
+            Piece::Token(token, source) => {
+                if i > 0 && previous.is_none_or(|previous| spaced(previous, token)) {
+                    text.push(' ');
+                }
+                text.push_str(source);
+                previous = Some(token);
+            }
+            Piece::Comment(comment) => {
+                if i > 0 {
+                    text.push(' ');
+                }
+                let lines: Vec<&str> = comment.split('\n').map(str::trim_end).collect();
+                text.push_str(&lines.join("\n"));
+                previous = None;
+            }
+        }
+    }
+    text
+}
+
+/// Whether a space separates two adjacent tokens
+fn spaced(previous: &Token, next: &Token) -> bool {
+    !matches!(
+        (previous, next),
+        (_, Token::Comma | Token::Colon | Token::RParen | Token::RBracket)
+            | (Token::LParen | Token::LBracket | Token::AttributeStart | Token::At | Token::Minus, _)
+            | (Token::Identifier(_), Token::LParen)
+            | (Token::LBrace, Token::RBrace)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_formats(source: &str, expected: &str) {
+        let formatted = format(source).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), formatted, "formatting is not idempotent");
+    }
+
+    #[test]
+    fn test_normalizes_layout() {
+        assert_formats(
+            "module   m\nsignal level(min,max) : real   \ncoil trip [latching,critical]\nrung r: when NO level(1,-5)AND(NC a OR NOT b)\n      then energise trip,de_energise x end",
+            "module m\nsignal level(min, max): real\ncoil trip [latching, critical]\n\nrung r:\n  when NO level(1, -5) AND (NC a OR NOT b)\n  then energise trip, de_energise x\n  end\n",
+        );
+    }
+
+    #[test]
+    fn test_preserves_comments() {
+        assert_formats(
+            "// header\nmodule m\nsignal a /* inline */ coil x   // trailing   \nrung r:\n    // why\n  when NO a\n /* multi\n    line */\n  then energise x\n// end of file",
+            "// header\nmodule m\nsignal a /* inline */\ncoil x // trailing\n\nrung r:\n  // why\n  when NO a\n  /* multi\n    line */\n  then energise x\n// end of file\n",
+        );
+    }
+
+    #[test]
+    fn test_blank_lines_group_declarations() {
+        assert_formats(
+            "\n\nmodule m\n\n\n\nsignal a\nsignal b\ncoil x\n// first rung\n#[if feature(\"debug\")]\nrung one:\n\n  when NO a\n\n  then energise x\nrung two:\n  when NO b\n  then energise x\nsignal c\n\n\n@deprecated(\"old\")\nsignal d\n\n\n",
+            "module m\n\nsignal a\nsignal b\ncoil x\n\n// first rung\n#[if feature(\"debug\")]\nrung one:\n  when NO a\n  then energise x\n\nrung two:\n  when NO b\n  then energise x\n\nsignal c\n\n@deprecated(\"old\")\nsignal d\n",
+        );
+    }
+
+    #[test]
+    fn test_indents_nested_sections() {
+        assert_formats(
+            "module m\nconstraints:\n      quality:\n            min_recall: 0.9\n      cost:\n            max_cost_per_submission: \"1 USD\"\nblock filter: {\ninputs: raw: real,\n    enable: bool\nimplementation: \"filters:smooth\"\n    }\nnetwork plant: {\n wires:\n sensor->filter_in\n}",
+            "module m\nconstraints:\n  quality:\n    min_recall: 0.9\n  cost:\n    max_cost_per_submission: \"1 USD\"\n\nblock filter: {\n  inputs: raw: real,\n    enable: bool\n  implementation: \"filters:smooth\"\n}\n\nnetwork plant: {\n  wires:\n    sensor -> filter_in\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_formatted_source_parses_the_same() {
+        let source = "module m\nsignal level: real\nsignal b\ncoil x\ncoil y\nrung r: when level > 0x1F AND NC b then energise x and de_energise y";
+        let formatted = format(source).unwrap();
+        let before = crate::parse(source).unwrap();
+        let after = crate::parse(&formatted).unwrap();
+        assert_eq!(before.rungs[0].guard.to_string(), after.rungs[0].guard.to_string());
+        assert!(formatted.contains("when level > 0x1F AND NC b\n  then energise x and de_energise y\n"));
+        assert!(format("module m\nsignal a $").is_err());
+    }
+}
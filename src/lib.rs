@@ -1,11 +1,45 @@
+//! Compiler for the Charta ladder-logic language.
+//!
+//! The crate root re-exports everything an embedder needs:
+//!
+//! ```no_run
+//! use charta_compiler::{compile_collecting, emitter_for, parse, Compilation, Diagnostic, EmitOptions, Emitter, Severity};
+//!
+//! let compilation: Compilation = compile_collecting("module m\nsignal a\ncoil b\nrung r: when NO a then energise b");
+//! for diagnostic in &compilation.diagnostics {
+//!     let _: &Diagnostic = diagnostic;
+//!     if diagnostic.severity == Severity::Error {
+//!         eprintln!("{}", diagnostic);
+//!     }
+//! }
+//!
+//! let module = parse("module m").unwrap();
+//! let emitter: Box<dyn Emitter> = emitter_for("yaml", EmitOptions::default()).unwrap();
+//! println!("{}", emitter.emit(&module).unwrap());
+//! ```
+
 pub mod parser;
 pub mod ast;
 pub mod resolver;
 pub mod emitter;
 pub mod error;
+pub mod diagnostic;
 pub mod cli;
+pub mod partial;
+pub mod validation;
+pub mod normalize;
+pub mod compilation;
+pub mod lower;
+pub mod codegen;
+pub mod eval;
+pub mod typecheck;
+pub mod formatter;
 
-pub use parser::parse;
+pub use parser::{parse, parse_all, parse_named, parse_with_warnings, tokenize, Span, Token};
 pub use error::{CompileError, Result};
-pub use resolver::resolve_names;
-pub use emitter::emit_ir;
+pub use diagnostic::{Diagnostic, Severity};
+pub use resolver::{resolve_lenient, resolve_names, resolve_names_with, ResolveOptions};
+pub use emitter::{emit_ir, emit_ir_binary, emit_ir_to, emit_ir_with, emit_ir_with_to, emitter_for, register_emitter, EmitOptions, Emitter};
+pub use validation::validate_ir_semantics;
+pub use typecheck::check_types;
+pub use compilation::{compile_collecting, compile_many, compile_named, Compilation};
@@ -0,0 +1,308 @@
+use crate::ast::{Action, ActionType, CoilDecl, ContactKind, ContactType, Expr, GuardExpr, Module, RungDecl};
+use crate::error::{CompileError, Result};
+use std::collections::{HashMap, HashSet};
+
+/// Lower a module to the primitive subset understood by every VM: guards of
+/// NO/NC/AND/OR/NOT and plain `energise`/`de_energise` actions.
+///
+/// - an `else` branch becomes a second rung, named `<rung>_else`, guarded by
+///   the negated guard
+/// - `energise c = true` becomes `energise c`, and `energise c = false`
+///   becomes `de_energise c`
+/// - `set c` and `reset c` become `energise c` and `de_energise c`, which a
+///   latching coil already holds
+/// - `P x` becomes `NO x AND NC x_prev` and `N x` becomes `NC x AND NO x_prev`,
+///   where the new latching coil `x_prev` is copied from `x` by rungs placed
+///   after all others
+///
+/// New rung and coil names get underscores appended until they are unique.
+/// Comparisons, `require` conditions and values other than `true`/`false`
+/// have no primitive form, so lowering a module that uses them is an error.
+pub fn lower_module(module: &Module) -> Result<Module> {
+    module.rungs.iter().try_for_each(check_lowerable)?;
+    let mut lowered = module.clone();
+    let mut lowering = Lowering::new(module);
+    lowered.rungs = Vec::new();
+    for rung in &module.rungs {
+        let rung = lowering.lower_edges(rung);
+        lowered.rungs.extend(lowering.lower_rung(&rung));
+    }
+    // Running last, the copies leave every edge above reading the previous scan
+    for update in std::mem::take(&mut lowering.updates) {
+        lowered.rungs.extend(lowering.lower_rung(&update));
+    }
+    lowered.coils.extend(lowering.coils);
+    Ok(lowered)
+}
+
+fn check_lowerable(rung: &RungDecl) -> Result<()> {
+    let unsupported = |what: String| Err(CompileError::emission(format!(
+        "Cannot lower rung {}: {} has no primitive form", rung.name, what
+    )));
+    if let Some(comparison) = find_comparison(&rung.guard) {
+        return unsupported(format!("comparison `{}`", comparison));
+    }
+    for action in rung.actions.iter().chain(&rung.else_actions) {
+        if let Some(condition) = &action.condition {
+            return unsupported(format!("`require {}`", condition));
+        }
+        if let Some(value) = action.value.as_ref().filter(|v| !matches!(v, Expr::Boolean(_))) {
+            return unsupported(format!("`energise {} = {}`", action.coil, value));
+        }
+    }
+    Ok(())
+}
+
+fn find_comparison(guard: &GuardExpr) -> Option<&GuardExpr> {
+    match guard {
+        GuardExpr::Compare { .. } => Some(guard),
+        GuardExpr::Contact { .. } => None,
+        GuardExpr::And { left, right } | GuardExpr::Or { left, right } => {
+            find_comparison(left).or_else(|| find_comparison(right))
+        }
+        GuardExpr::Not { expr } => find_comparison(expr),
+    }
+}
+
+/// Names in use, and the memory coils recording what each edge contact read
+/// on the previous scan
+struct Lowering<'a> {
+    module: &'a Module,
+    /// Signal, coil and rung names already in use
+    taken: HashSet<String>,
+    coils: Vec<CoilDecl>,
+    /// Memory coil of each edge-read signal or coil
+    memories: HashMap<String, String>,
+    /// Rungs copying each edge-read contact into its memory coil
+    updates: Vec<RungDecl>,
+    /// Text of the contacts `updates` already copy
+    copied: HashSet<String>,
+}
+
+impl<'a> Lowering<'a> {
+    fn new(module: &'a Module) -> Self {
+        let taken = module.signals.iter().map(|s| s.name.clone())
+            .chain(module.coils.iter().map(|c| c.name.clone()))
+            .chain(module.rungs.iter().map(|r| r.name.clone()))
+            .collect();
+        Lowering {
+            module,
+            taken,
+            coils: Vec::new(),
+            memories: HashMap::new(),
+            updates: Vec::new(),
+            copied: HashSet::new(),
+        }
+    }
+    
+    /// `base`, or `base` with underscores appended until no name clashes
+    fn fresh(&mut self, base: String) -> String {
+        let mut name = base;
+        while self.taken.contains(&name) {
+            name.push('_');
+        }
+        self.taken.insert(name.clone());
+        name
+    }
+    
+    fn lower_rung(&mut self, rung: &RungDecl) -> Vec<RungDecl> {
+        let mut rungs = vec![RungDecl {
+            name: rung.name.clone(),
+            guard: rung.guard.clone(),
+            actions: rung.actions.iter().map(lower_action).collect(),
+            else_actions: Vec::new(),
+            feature: rung.feature.clone(),
+        }];
+        if !rung.else_actions.is_empty() {
+            rungs.push(RungDecl {
+                name: self.fresh(format!("{}_else", rung.name)),
+                guard: GuardExpr::Not {
+                    expr: Box::new(rung.guard.clone()),
+                },
+                actions: rung.else_actions.iter().map(lower_action).collect(),
+                else_actions: Vec::new(),
+                feature: rung.feature.clone(),
+            });
+        }
+        rungs
+    }
+    
+    fn lower_edges(&mut self, rung: &RungDecl) -> RungDecl {
+        let mut lowered = rung.clone();
+        lowered.guard = self.lower_guard(&rung.guard);
+        for action in lowered.actions.iter_mut().chain(&mut lowered.else_actions) {
+            if let Some(condition) = &action.condition {
+                action.condition = Some(self.lower_guard(condition));
+            }
+        }
+        lowered
+    }
+    
+    fn lower_guard(&mut self, guard: &GuardExpr) -> GuardExpr {
+        match guard {
+            GuardExpr::Contact { name, contact_type, arguments, kind } if contact_type.is_edge() => {
+                let current = contact(name, ContactType::NO, arguments, *kind);
+                let memory = self.memory_coil(&current, name, arguments);
+                let (now, before) = match contact_type {
+                    ContactType::RisingEdge => (ContactType::NO, ContactType::NC),
+                    _ => (ContactType::NC, ContactType::NO),
+                };
+                GuardExpr::And {
+                    left: Box::new(contact(name, now, arguments, *kind)),
+                    right: Box::new(contact(&memory, before, arguments, Some(ContactKind::Coil))),
+                }
+            }
+            GuardExpr::And { left, right } => GuardExpr::And {
+                left: Box::new(self.lower_guard(left)),
+                right: Box::new(self.lower_guard(right)),
+            },
+            GuardExpr::Or { left, right } => GuardExpr::Or {
+                left: Box::new(self.lower_guard(left)),
+                right: Box::new(self.lower_guard(right)),
+            },
+            GuardExpr::Not { expr } => GuardExpr::Not {
+                expr: Box::new(self.lower_guard(expr)),
+            },
+            GuardExpr::Contact { .. } | GuardExpr::Compare { .. } => guard.clone(),
+        }
+    }
+    
+    /// Name of the coil holding `current` from the previous scan, declaring it
+    /// and the rung that copies it on first use
+    fn memory_coil(&mut self, current: &GuardExpr, name: &str, arguments: &[Expr]) -> String {
+        let memory = match self.memories.get(name) {
+            Some(memory) => memory.clone(),
+            None => {
+                let memory = self.fresh(format!("{}_prev", name));
+                let parameters = self.module.signals.iter().find(|s| s.name == name).map(|s| s.parameters.clone())
+                    .or_else(|| self.module.coils.iter().find(|c| c.name == name).map(|c| c.parameters.clone()))
+                    .unwrap_or_default();
+                self.coils.push(CoilDecl {
+                    name: memory.clone(),
+                    parameters,
+                    latching: Some(true),
+                    critical: None,
+                    unit: None,
+                    feature: None,
+                    deprecated: None,
+                });
+                self.memories.insert(name.to_string(), memory.clone());
+                memory
+            }
+        };
+        if self.copied.insert(current.to_string()) {
+            let copy = |action_type| Action {
+                action_type,
+                coil: memory.clone(),
+                arguments: arguments.to_vec(),
+                value: None,
+                condition: None,
+            };
+            let name = self.fresh(format!("{}_update", memory));
+            self.updates.push(RungDecl {
+                name,
+                guard: current.clone(),
+                actions: vec![copy(ActionType::Energise)],
+                else_actions: vec![copy(ActionType::DeEnergise)],
+                feature: None,
+            });
+        }
+        memory
+    }
+}
+
+fn contact(name: &str, contact_type: ContactType, arguments: &[Expr], kind: Option<ContactKind>) -> GuardExpr {
+    GuardExpr::Contact {
+        name: name.to_string(),
+        contact_type,
+        arguments: arguments.to_vec(),
+        kind,
+    }
+}
+
+fn lower_action(action: &Action) -> Action {
+    let mut lowered = action.clone();
+    match action.value {
+        Some(Expr::Boolean(true)) => lowered.value = None,
+        Some(Expr::Boolean(false)) if action.action_type == ActionType::Energise => {
+            lowered.action_type = ActionType::DeEnergise;
+            lowered.value = None;
+        }
+        _ => {}
+    }
+    match action.action_type {
+        ActionType::Set => lowered.action_type = ActionType::Energise,
+        ActionType::Reset => lowered.action_type = ActionType::DeEnergise,
+        _ => {}
+    }
+    lowered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_else_lowers_to_negated_rung() {
+        let module = crate::parse("module m\nrung r:\n  when NO a AND NO b\n  then energise x\n  else de_energise x\n  end").unwrap();
+        let lowered = lower_module(&module).unwrap();
+        assert_eq!(lowered.rungs.len(), 2);
+        assert!(lowered.rungs[0].else_actions.is_empty());
+        
+        let else_rung = &lowered.rungs[1];
+        assert_eq!(else_rung.name, "r_else");
+        assert_eq!(else_rung.guard.to_string(), "NOT (NO a AND NO b)");
+        assert_eq!(else_rung.actions[0].action_type, ActionType::DeEnergise);
+        
+        let module = crate::parse("module m\nrung r:\n  when NO a\n  then energise x\n  else de_energise x\n  end\nrung r_else:\n  when NO b\n  then energise y").unwrap();
+        let names: Vec<String> = lower_module(&module).unwrap().rungs.into_iter().map(|r| r.name).collect();
+        assert_eq!(names, ["r", "r_else_", "r_else"]);
+    }
+
+    #[test]
+    fn test_constructs_without_primitive_form_are_rejected() {
+        let lower = |rung: &str| {
+            let module = crate::parse(&format!("module m\nrung r:\n  {}", rung)).unwrap();
+            lower_module(&module).map(|_| ()).map_err(|e| e.to_string())
+        };
+        assert_eq!(lower("when level > 5\n  then energise x").unwrap_err(), "IR emission error: Cannot lower rung r: comparison `level > 5` has no primitive form");
+        assert_eq!(lower("when NO a\n  then require NO b").unwrap_err(), "IR emission error: Cannot lower rung r: `require NO b` has no primitive form");
+        assert_eq!(lower("when NO a\n  then energise x = a").unwrap_err(), "IR emission error: Cannot lower rung r: `energise x = a` has no primitive form");
+        assert!(lower("when NO a\n  then require operator_ack").is_ok());
+    }
+
+    #[test]
+    fn test_set_and_reset_lower_to_energise() {
+        let module = crate::parse("module m\nrung r:\n  when NO a\n  then set x, reset y").unwrap();
+        let actions = &lower_module(&module).unwrap().rungs[0].actions;
+        assert_eq!(actions[0].action_type, ActionType::Energise);
+        assert_eq!(actions[1].action_type, ActionType::DeEnergise);
+    }
+
+    #[test]
+    fn test_edges_lower_to_memory_coils() {
+        let module = crate::parse("module m\nsignal start\nsignal start_prev\ncoil x\nrung r:\n  when P start AND NOT N start\n  then energise x").unwrap();
+        let lowered = lower_module(&module).unwrap();
+        assert_eq!(lowered.rungs[0].guard.to_string(), "NO start AND NC start_prev_ AND NOT (NC start AND NO start_prev_)");
+        
+        let memory = lowered.coils.last().unwrap();
+        assert_eq!(memory.name, "start_prev_");
+        assert_eq!(memory.latching, Some(true));
+        
+        let names: Vec<&str> = lowered.rungs.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["r", "start_prev__update", "start_prev__update_else"]);
+        assert_eq!(lowered.rungs[1].guard.to_string(), "NO start");
+        assert_eq!(lowered.rungs[1].actions[0].action_type, ActionType::Energise);
+        assert_eq!(lowered.rungs[2].actions[0].action_type, ActionType::DeEnergise);
+    }
+
+    #[test]
+    fn test_explicit_false_lowers_to_de_energise() {
+        let module = crate::parse("module m\nrung r:\n  when NO a\n  then energise x = false, energise y = true").unwrap();
+        let actions = &lower_module(&module).unwrap().rungs[0].actions;
+        assert_eq!(actions[0].action_type, ActionType::DeEnergise);
+        assert!(actions[0].value.is_none());
+        assert_eq!(actions[1].action_type, ActionType::Energise);
+        assert!(actions[1].value.is_none());
+    }
+}
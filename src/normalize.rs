@@ -0,0 +1,139 @@
+use crate::ast::{ContactType, GuardExpr};
+
+/// Canonical forms of guard expressions, for analyses that compare guards.
+///
+/// Normalization pushes `NOT` inward (De Morgan, double negation, `NOT NO x`
+/// becoming `NC x` and `NOT x > 1` becoming `x <= 1`), flattens nested
+/// `AND`/`OR` chains, and sorts and de-duplicates their operands so that
+/// commutative rewrites compare equal.
+pub fn normalize_guard(guard: &GuardExpr) -> GuardExpr {
+    canonicalize(push_negation(guard, false))
+}
+
+/// Whether two guards have the same canonical form.
+///
+/// This is a structural check: it recognises commutativity, De Morgan
+/// rewrites and duplicated operands, but not every logical equivalence.
+pub fn guards_equivalent(a: &GuardExpr, b: &GuardExpr) -> bool {
+    normalize_guard(a).to_string() == normalize_guard(b).to_string()
+}
+
+fn push_negation(guard: &GuardExpr, negate: bool) -> GuardExpr {
+    match guard {
+        // An edge has no complementary contact, so its negation stays explicit
+        GuardExpr::Contact { contact_type, .. } if negate && contact_type.is_edge() => GuardExpr::Not {
+            expr: Box::new(guard.clone()),
+        },
+        GuardExpr::Contact { name, contact_type, arguments, kind } => GuardExpr::Contact {
+            name: name.clone(),
+            contact_type: match (contact_type, negate) {
+                (ContactType::NO, true) => ContactType::NC,
+                (ContactType::NC, true) => ContactType::NO,
+                // Negated edges are handled above
+                (contact_type, _) => *contact_type,
+            },
+            arguments: arguments.clone(),
+            kind: *kind,
+        },
+        GuardExpr::Not { expr } => push_negation(expr, !negate),
+        GuardExpr::Compare { left, op, right } => GuardExpr::Compare {
+            left: left.clone(),
+            op: if negate { op.negated() } else { *op },
+            right: right.clone(),
+        },
+        GuardExpr::And { left, right } if negate => GuardExpr::Or {
+            left: Box::new(push_negation(left, true)),
+            right: Box::new(push_negation(right, true)),
+        },
+        GuardExpr::Or { left, right } if negate => GuardExpr::And {
+            left: Box::new(push_negation(left, true)),
+            right: Box::new(push_negation(right, true)),
+        },
+        GuardExpr::And { left, right } => GuardExpr::And {
+            left: Box::new(push_negation(left, false)),
+            right: Box::new(push_negation(right, false)),
+        },
+        GuardExpr::Or { left, right } => GuardExpr::Or {
+            left: Box::new(push_negation(left, false)),
+            right: Box::new(push_negation(right, false)),
+        },
+    }
+}
+
+fn canonicalize(guard: GuardExpr) -> GuardExpr {
+    match guard {
+        GuardExpr::And { .. } => rebuild(&guard, true),
+        GuardExpr::Or { .. } => rebuild(&guard, false),
+        GuardExpr::Not { expr } => GuardExpr::Not {
+            expr: Box::new(canonicalize(*expr)),
+        },
+        contact => contact,
+    }
+}
+
+/// Flatten an `AND` (or `OR`) chain, then rebuild it with sorted, unique operands
+fn rebuild(guard: &GuardExpr, is_and: bool) -> GuardExpr {
+    let mut operands = Vec::new();
+    flatten(guard, is_and, &mut operands);
+    let mut operands: Vec<GuardExpr> = operands.into_iter().map(canonicalize).collect();
+    operands.sort_by_cached_key(|operand| operand.to_string());
+    operands.dedup_by(|a, b| a.to_string() == b.to_string());
+    
+    let mut operands = operands.into_iter();
+    let first = operands.next().expect("a chain has at least one operand");
+    operands.fold(first, |left, right| {
+        if is_and {
+            GuardExpr::And { left: Box::new(left), right: Box::new(right) }
+        } else {
+            GuardExpr::Or { left: Box::new(left), right: Box::new(right) }
+        }
+    })
+}
+
+fn flatten(guard: &GuardExpr, is_and: bool, operands: &mut Vec<GuardExpr>) {
+    match (guard, is_and) {
+        (GuardExpr::And { left, right }, true) | (GuardExpr::Or { left, right }, false) => {
+            flatten(left, is_and, operands);
+            flatten(right, is_and, operands);
+        }
+        _ => operands.push(guard.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(source: &str) -> GuardExpr {
+        let module = crate::parse(&format!("module m\nrung r:\n  when {}\n  then energise c", source)).unwrap();
+        module.rungs[0].guard.clone()
+    }
+
+    #[test]
+    fn test_commutative_operands_normalize_equal() {
+        assert_eq!(normalize_guard(&guard("a AND b")).to_string(), normalize_guard(&guard("b AND a")).to_string());
+        assert!(guards_equivalent(&guard("a AND (b AND c)"), &guard("(c AND a) AND b")));
+        assert!(guards_equivalent(&guard("a OR b"), &guard("b OR a OR b")));
+        assert!(!guards_equivalent(&guard("a AND b"), &guard("a OR b")));
+    }
+
+    #[test]
+    fn test_de_morgan_forms_are_equivalent() {
+        assert!(guards_equivalent(&guard("NOT (a AND b)"), &guard("NC a OR NC b")));
+        assert!(guards_equivalent(&guard("NOT (a OR b)"), &guard("NOT a AND NOT b")));
+        assert!(guards_equivalent(&guard("NOT (NOT a)"), &guard("NO a")));
+        assert_eq!(normalize_guard(&guard("NOT (a AND b)")).to_string(), "NC a OR NC b");
+    }
+
+    #[test]
+    fn test_negated_edge_stays_negated() {
+        assert_eq!(normalize_guard(&guard("NOT (P a AND NO b)")).to_string(), "NC b OR NOT P a");
+        assert!(!guards_equivalent(&guard("NOT P a"), &guard("N a")));
+    }
+
+    #[test]
+    fn test_negated_comparison_flips_operator() {
+        assert_eq!(normalize_guard(&guard("NOT level > 80")).to_string(), "level <= 80");
+        assert!(guards_equivalent(&guard("NOT (a == 1 OR b < 2)"), &guard("a != 1 AND b >= 2")));
+    }
+}
@@ -1,14 +1,141 @@
 use logos::Logos;
+use std::ops::Range;
 use crate::ast::*;
+use crate::diagnostic::Diagnostic;
 use crate::error::{CompileError, Result};
 
+/// Errors raised by the lexer for malformed input
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum LexError {
+    #[default]
+    InvalidToken,
+    NumberOutOfRange,
+    MalformedNumber,
+    UnterminatedComment,
+    NestedComment,
+    /// An unknown or malformed `\` escape, holding its source text
+    InvalidEscape(String),
+}
+
+/// Largest integer an `f64` represents exactly (2^53)
+const MAX_EXACT_INTEGER: u64 = 1 << 53;
+
+/// Validate `_` digit separators and strip them.
+///
+/// Each digit group must be non-empty, and separators may only sit between digits.
+fn strip_separators(text: &str) -> std::result::Result<String, LexError> {
+    for group in text.split('.') {
+        if group.is_empty() || group.starts_with('_') || group.ends_with('_') || group.contains("__") {
+            return Err(LexError::MalformedNumber);
+        }
+    }
+    Ok(text.replace('_', ""))
+}
+
+fn lex_number(lex: &mut logos::Lexer<Token>) -> std::result::Result<f64, LexError> {
+    let radix = match lex.slice().get(..2) {
+        Some("0x") => Some(16),
+        Some("0o") => Some(8),
+        Some("0b") => Some(2),
+        _ => None,
+    };
+    if let Some(radix) = radix {
+        let digits = strip_separators(&lex.slice()[2..])?;
+        return match u64::from_str_radix(&digits, radix) {
+            Ok(n) if n <= MAX_EXACT_INTEGER => Ok(n as f64),
+            Ok(_) => Err(LexError::NumberOutOfRange),
+            Err(e) if *e.kind() == std::num::IntErrorKind::PosOverflow => Err(LexError::NumberOutOfRange),
+            Err(_) => Err(LexError::MalformedNumber),
+        };
+    }
+    let text = strip_separators(lex.slice())?;
+    if !text.contains('.') {
+        // Integers must survive the conversion to f64 without rounding
+        match text.parse::<u64>() {
+            Ok(n) if n <= MAX_EXACT_INTEGER => {}
+            _ => return Err(LexError::NumberOutOfRange),
+        }
+    }
+    match text.parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(LexError::NumberOutOfRange),
+    }
+}
+
+/// Strip the quotes from a string literal and translate its escapes
+fn lex_string(lex: &mut logos::Lexer<Token>) -> std::result::Result<String, LexError> {
+    let slice = lex.slice();
+    let mut chars = slice[1..slice.len() - 1].chars();
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('"') => text.push('"'),
+            Some('\\') => text.push('\\'),
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some('r') => text.push('\r'),
+            Some('u') => {
+                let rest = chars.as_str();
+                let escape = match rest.find('}') {
+                    Some(end) if rest.starts_with('{') => &rest[..=end],
+                    _ => return Err(LexError::InvalidEscape("\\u".to_string())),
+                };
+                let digits = &escape[1..escape.len() - 1];
+                let decoded = if (1..=6).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    u32::from_str_radix(digits, 16).ok().and_then(char::from_u32)
+                } else {
+                    None
+                };
+                match decoded {
+                    Some(decoded) => text.push(decoded),
+                    None => return Err(LexError::InvalidEscape(format!("\\u{}", escape))),
+                }
+                chars = rest[escape.len()..].chars();
+            }
+            Some(other) => return Err(LexError::InvalidEscape(format!("\\{}", other))),
+            None => return Err(LexError::InvalidEscape("\\".to_string())),
+        }
+    }
+    Ok(text)
+}
+
+/// Skip a `/* ... */` comment, which may span lines but not nest
+fn block_comment(lex: &mut logos::Lexer<Token>) -> logos::FilterResult<(), LexError> {
+    let remainder = lex.remainder();
+    match remainder.find("*/") {
+        Some(end) => {
+            lex.bump(end + 2);
+            if remainder[..end].contains("/*") {
+                logos::FilterResult::Error(LexError::NestedComment)
+            } else {
+                logos::FilterResult::Skip
+            }
+        }
+        None => {
+            lex.bump(remainder.len());
+            logos::FilterResult::Error(LexError::UnterminatedComment)
+        }
+    }
+}
+
 #[derive(Logos, Debug, PartialEq, Clone)]
+#[logos(error = LexError)]
 #[logos(skip r"[ \t\r\n]+")]
 #[logos(skip r"//[^\n]*")]
 pub enum Token {
+    /// Never produced: `block_comment` skips the comment or reports an error
+    #[token("/*", block_comment)]
+    BlockComment,
+    
     // Keywords
     #[token("module")]
     Module,
+    #[token("library")]
+    Library,
     #[token("signal")]
     Signal,
     #[token("coil")]
@@ -17,6 +144,8 @@ pub enum Token {
     Rung,
     #[token("block")]
     Block,
+    #[token("abstract")]
+    Abstract,
     #[token("network")]
     Network,
     #[token("when")]
@@ -25,6 +154,8 @@ pub enum Token {
     Then,
     #[token("else")]
     Else,
+    #[token("end")]
+    End,
     #[token("energise")]
     Energise,
     #[token("de_energise")]
@@ -33,10 +164,18 @@ pub enum Token {
     Escalate,
     #[token("require")]
     Require,
+    #[token("set")]
+    Set,
+    #[token("reset")]
+    Reset,
     #[token("NO")]
     NO,
     #[token("NC")]
     NC,
+    #[token("P", priority = 3)]
+    P,
+    #[token("N", priority = 3)]
+    N,
     #[token("AND")]
     And,
     #[token("OR")]
@@ -63,10 +202,13 @@ pub enum Token {
     Wires,
     
     // Literals
-    #[regex(r#""([^"\\]|\\")*""#, |lex| lex.slice()[1..lex.slice().len()-1].replace("\\\"", "\"").replace("\\\\", "\\"))]
+    #[regex(r#""([^"\\]|\\.)*""#, lex_string)]
     String(String),
     
-    #[regex(r"[0-9]+(\.[0-9]+)?", |lex| lex.slice().parse().ok())]
+    #[regex(r"[0-9][0-9_]*(\.[0-9_]+)?", lex_number)]
+    #[regex(r"0x[0-9a-zA-Z_]*", lex_number)]
+    #[regex(r"0o[0-9_]*", lex_number)]
+    #[regex(r"0b[0-9_]*", lex_number)]
     Number(f64),
     
     #[token("true")]
@@ -79,6 +221,10 @@ pub enum Token {
     Identifier(String),
     
     // Operators and punctuation
+    #[token("#[")]
+    AttributeStart,
+    #[token("@")]
+    At,
     #[token(":")]
     Colon,
     #[token(",")]
@@ -99,37 +245,300 @@ pub enum Token {
     Equals,
     #[token("->")]
     Arrow,
+    #[token(">")]
+    Greater,
+    #[token("<")]
+    Less,
+    #[token(">=")]
+    GreaterEq,
+    #[token("<=")]
+    LessEq,
+    #[token("==")]
+    EqEq,
+    #[token("!=")]
+    NotEq,
     #[token("-")]
     Minus,
 }
 
+impl Token {
+    /// Source text of a reserved word that could be mistaken for a name
+    pub fn keyword(&self) -> Option<&'static str> {
+        Some(match self {
+            Token::Module => "module",
+            Token::Library => "library",
+            Token::Signal => "signal",
+            Token::Coil => "coil",
+            Token::Rung => "rung",
+            Token::Block => "block",
+            Token::Abstract => "abstract",
+            Token::Network => "network",
+            Token::When => "when",
+            Token::Then => "then",
+            Token::Else => "else",
+            Token::End => "end",
+            Token::Energise => "energise",
+            Token::DeEnergise => "de_energise",
+            Token::Escalate => "escalate",
+            Token::Require => "require",
+            Token::Set => "set",
+            Token::Reset => "reset",
+            Token::P => "P",
+            Token::N => "N",
+            Token::Inputs => "inputs",
+            Token::Outputs => "outputs",
+            Token::Internals => "internals",
+            Token::Implementation => "implementation",
+            Token::Effect => "effect",
+            Token::Context => "context",
+            Token::Intent => "intent",
+            Token::Constraints => "constraints",
+            Token::Wires => "wires",
+            Token::True => "true",
+            Token::False => "false",
+            _ => return None,
+        })
+    }
+}
+
+/// Location of a token in the source text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize, // byte offset of the first character
+    pub end: usize,   // byte offset one past the last character
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Maps byte offsets to 1-based line and column numbers
+struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { source, line_starts }
+    }
+    
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let line_index = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.source[self.line_starts[line_index]..offset].chars().count() + 1;
+        (line_index + 1, column)
+    }
+}
+
+/// Lex source into tokens with their spans, skipping invalid input
+pub fn lex(source: &str) -> Vec<(Token, Span)> {
+    let index = LineIndex::new(source);
+    let mut lexer = Token::lexer(source);
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next() {
+        if let Ok(tok) = token {
+            let range = lexer.span();
+            let (line, column) = index.position(range.start);
+            tokens.push((tok, Span {
+                start: range.start,
+                end: range.end,
+                line,
+                column,
+            }));
+        }
+    }
+    tokens
+}
+
+/// Lex source into tokens with their spans, failing on the first lexer error
+pub fn tokenize(source: &str) -> Result<Vec<(Token, Span)>> {
+    let index = LineIndex::new(source);
+    let mut lexer = Token::lexer(source);
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next() {
+        let range = lexer.span();
+        let (line, column) = index.position(range.start);
+        match token {
+            Ok(tok) => tokens.push((tok, Span {
+                start: range.start,
+                end: range.end,
+                line,
+                column,
+            })),
+            Err(error) => return Err(lex_error_at(error, lexer.slice(), line, column)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Describe a lexer error on the literal `slice` as a parse error
+fn lex_error_at(error: LexError, slice: &str, line: usize, column: usize) -> CompileError {
+    let message = match error {
+        LexError::InvalidToken => format!("Unexpected character `{}`", slice),
+        LexError::NumberOutOfRange | LexError::MalformedNumber => {
+            let shown = if slice.len() > 20 {
+                format!("{}...", &slice[..20])
+            } else {
+                slice.to_string()
+            };
+            let problem = if error == LexError::NumberOutOfRange {
+                "Number literal out of range"
+            } else {
+                "Malformed number literal"
+            };
+            format!("{}: {}", problem, shown)
+        }
+        LexError::UnterminatedComment => "Unterminated block comment: `/*` has no closing `*/`".to_string(),
+        LexError::NestedComment => "Nested block comments are not supported".to_string(),
+        LexError::InvalidEscape(escape) => format!("Unknown escape sequence `{}` in string", escape),
+    };
+    CompileError::Parse {
+        line,
+        column,
+        message,
+        file: None,
+        suggestion: None,
+    }
+}
+
+/// Tokens on 1-based `line` in source order, paired with their columns
+pub fn tokens_at_line(source: &str, line: usize) -> Vec<(Token, usize)> {
+    lex(source).into_iter()
+        .filter(|(_, span)| span.line == line)
+        .map(|(token, span)| (token, span.column))
+        .collect()
+}
+
+/// Lines of top-level declarations and attributes separated from the
+/// previous declaration by at least one blank line.
+///
+/// This is the blank-line trivia a formatter needs to keep declaration
+/// groups apart; runs of several blank lines count as one break.
+pub fn group_breaks(source: &str) -> Vec<usize> {
+    let tokens = lex(source);
+    let mut breaks = Vec::new();
+    for (i, (token, span)) in tokens.iter().enumerate() {
+        let Some((previous, previous_span)) = i.checked_sub(1).map(|p| &tokens[p]) else {
+            continue;
+        };
+        let starts_declaration = matches!(
+            token,
+            Token::Signal | Token::Coil | Token::Rung | Token::Block | Token::Network | Token::Abstract
+                | Token::Context | Token::Intent | Token::Constraints | Token::AttributeStart | Token::At
+        ) && !matches!(previous, Token::Abstract);
+        if !starts_declaration {
+            continue;
+        }
+        // Lines strictly between the two tokens; comments make a line non-blank
+        let gap: Vec<&str> = source[previous_span.end..span.start].split('\n').collect();
+        if gap.len() > 2 && gap[1..gap.len() - 1].iter().any(|line| line.trim().is_empty()) {
+            breaks.push(span.line);
+        }
+    }
+    breaks
+}
+
+/// Operators on the longest path from the root of `guard` to a contact
+fn guard_height(guard: &GuardExpr) -> usize {
+    match guard {
+        GuardExpr::Contact { .. } | GuardExpr::Compare { .. } => 0,
+        GuardExpr::And { left, right } | GuardExpr::Or { left, right } => 1 + guard_height(left).max(guard_height(right)),
+        GuardExpr::Not { expr } => 1 + guard_height(expr),
+    }
+}
+
+/// Update `old_tokens` after an edit without re-lexing the whole buffer.
+///
+/// `source` is the text after the edit, `edit_range` is the byte range of the
+/// old text that was replaced and `inserted_len` the byte length of its
+/// replacement. Lexing restarts at the last token that ends before the edit
+/// and stops once it lines up with an unchanged old token again; the rest of
+/// the old tokens are shifted. An edit that never lines up again, such as one
+/// opening a string or comment, is lexed to the end. The result matches
+/// `lex(source)`.
+pub fn relex_range(source: &str, old_tokens: &[(Token, Span)], edit_range: Range<usize>, inserted_len: usize) -> Vec<(Token, Span)> {
+    let index = LineIndex::new(source);
+    let shift = |offset: usize| offset + inserted_len - edit_range.len();
+    let edit_end = edit_range.start + inserted_len;
+    
+    // Tokens touching the edit may merge with the new text, so keep only those strictly before it
+    let kept = old_tokens.partition_point(|(_, span)| span.end < edit_range.start);
+    let mut tokens = old_tokens[..kept].to_vec();
+    let restart = tokens.last().map_or(0, |(_, span)| span.end);
+    let mut old = old_tokens[kept..].iter().skip_while(|(_, span)| span.start < edit_range.end).peekable();
+    
+    let mut lexer = Token::lexer(&source[restart..]);
+    let mut synced = false;
+    while let Some(token) = lexer.next() {
+        let range = lexer.span();
+        let (start, end) = (restart + range.start, restart + range.end);
+        
+        // Drop old tokens the new ones have passed, then check whether we are back in sync
+        while old.peek().is_some_and(|(_, span)| shift(span.start) < start) {
+            old.next();
+        }
+        if start >= edit_end {
+            if let Some((old_token, span)) = old.peek() {
+                if shift(span.start) == start && shift(span.end) == end && Ok(old_token) == token.as_ref() {
+                    synced = true;
+                    break;
+                }
+            }
+        }
+        
+        if let Ok(tok) = token {
+            let (line, column) = index.position(start);
+            tokens.push((tok, Span { start, end, line, column }));
+        }
+    }
+    
+    for (token, span) in old.filter(|_| synced) {
+        let (start, end) = (shift(span.start), shift(span.end));
+        let (line, column) = index.position(start);
+        tokens.push((token.clone(), Span { start, end, line, column }));
+    }
+    tokens
+}
+
 pub struct Parser {
     tokens: Vec<(Token, usize, usize)>, // (token, line, column)
     pos: usize,
+    warnings: Vec<Diagnostic>,
+    lex_error: Option<CompileError>,
+    /// Error cap when recovering at declaration boundaries; `None` stops at the first error
+    max_errors: Option<usize>,
+    errors: Vec<CompileError>,
+    suppressed: bool,
+    guard_depth: usize,
+    max_guard_depth: usize,
 }
 
+/// Default limit on guard nesting, applied both to parentheses and to the
+/// `AND`/`OR`/`NOT` operators stacked above any contact. IR nests one JSON
+/// object per operator and `serde_json` loads at most 128 levels, so this
+/// leaves room for the enclosing document.
+pub const DEFAULT_MAX_GUARD_DEPTH: usize = 100;
+
 impl Parser {
     pub fn new(source: &str) -> Self {
+        let index = LineIndex::new(source);
         let mut lexer = Token::lexer(source);
         let mut tokens = Vec::new();
-        let mut line = 1;
-        let mut column = 1;
+        let mut lex_error = None;
         
         while let Some(token) = lexer.next() {
+            let (line, column) = index.position(lexer.span().start);
             match token {
                 Ok(tok) => {
-                    let col = column;
-                    // Estimate column (simplified)
-                    column += lexer.slice().len();
-                    if lexer.slice().contains('\n') {
-                        line += lexer.slice().matches('\n').count();
-                        column = 1;
-                    }
-                    tokens.push((tok, line, col));
+                    tokens.push((tok, line, column));
                 }
-                Err(_) => {
-                    // Skip invalid tokens for now
-                    column += 1;
+                // Skip invalid tokens for now
+                Err(LexError::InvalidToken) => {}
+                Err(error) => {
+                    if lex_error.is_none() {
+                        lex_error = Some(lex_error_at(error, lexer.slice(), line, column));
+                    }
                 }
             }
         }
@@ -137,6 +546,13 @@ impl Parser {
         Self {
             tokens,
             pos: 0,
+            warnings: Vec::new(),
+            lex_error,
+            max_errors: None,
+            errors: Vec::new(),
+            suppressed: false,
+            guard_depth: 0,
+            max_guard_depth: DEFAULT_MAX_GUARD_DEPTH,
         }
     }
     
@@ -154,6 +570,67 @@ impl Parser {
         }
     }
     
+    /// Position of the next token, or of the last token at end of input
+    fn current_position(&self) -> (usize, usize) {
+        self.tokens.get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map(|(_, line, column)| (*line, *column))
+            .unwrap_or((1, 1))
+    }
+    
+    fn error_here(&self, message: impl Into<String>) -> CompileError {
+        let (line, column) = self.current_position();
+        CompileError::Parse {
+            line,
+            column,
+            message: message.into(),
+            file: None,
+            suggestion: None,
+        }
+    }
+    
+    /// Parse a parenthesized, comma-separated list of parameter names.
+    /// A trailing comma is allowed; comments and newlines are skipped by the lexer.
+    fn parse_parameters(&mut self) -> Result<Vec<String>> {
+        let mut parameters = Vec::new();
+        self.expect(Token::LParen)?;
+        while self.peek() != Some(&Token::RParen) {
+            match self.peek() {
+                Some(Token::Identifier(param)) => {
+                    parameters.push(param.clone());
+                    self.next();
+                }
+                Some(tok) => {
+                    return Err(self.error_here(format!("Expected parameter name, found {:?}", tok)));
+                }
+                None => break,
+            }
+            if self.peek() == Some(&Token::Comma) {
+                self.next();
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::RParen)?;
+        Ok(parameters)
+    }
+    
+    /// Parse a parenthesized, comma-separated list of argument expressions
+    fn parse_arguments(&mut self) -> Result<Vec<Expr>> {
+        let mut arguments = Vec::new();
+        self.expect(Token::LParen)?;
+        while self.peek() != Some(&Token::RParen) {
+            arguments.push(self.parse_expr()?);
+            if self.peek() == Some(&Token::Comma) {
+                self.next();
+            } else {
+                break;
+            }
+        }
+        self.expect(Token::RParen)?;
+        Ok(arguments)
+    }
+    
     fn expect(&mut self, expected: Token) -> Result<Token> {
         match self.next() {
             Some(tok) => {
@@ -163,19 +640,34 @@ impl Parser {
                     (Token::Signal, Token::Signal) => true,
                     (Token::Coil, Token::Coil) => true,
                     (Token::Rung, Token::Rung) => true,
+                    (Token::Block, Token::Block) => true,
+                    (Token::Network, Token::Network) => true,
                     (Token::When, Token::When) => true,
                     (Token::Then, Token::Then) => true,
                     (Token::Energise, Token::Energise) => true,
                     (Token::DeEnergise, Token::DeEnergise) => true,
+                    (Token::Set, Token::Set) => true,
+                    (Token::Reset, Token::Reset) => true,
                     (Token::NO, Token::NO) => true,
                     (Token::NC, Token::NC) => true,
+                    (Token::P, Token::P) => true,
+                    (Token::N, Token::N) => true,
                     (Token::And, Token::And) => true,
                     (Token::Or, Token::Or) => true,
                     (Token::Not, Token::Not) => true,
+                    (Token::Intent, Token::Intent) => true,
+                    (Token::Constraints, Token::Constraints) => true,
                     (Token::Colon, Token::Colon) => true,
                     (Token::Comma, Token::Comma) => true,
                     (Token::LParen, Token::LParen) => true,
                     (Token::RParen, Token::RParen) => true,
+                    (Token::AttributeStart, Token::AttributeStart) => true,
+                    (Token::At, Token::At) => true,
+                    (Token::RBracket, Token::RBracket) => true,
+                    (Token::LBrace, Token::LBrace) => true,
+                    (Token::Arrow, Token::Arrow) => true,
+                    (Token::Equals, Token::Equals) => true,
+                    (Token::RBrace, Token::RBrace) => true,
                     (Token::Identifier(_), Token::Identifier(_)) => true,
                     (Token::String(_), Token::String(_)) => true,
                     (Token::Number(_), Token::Number(_)) => true,
@@ -192,6 +684,8 @@ impl Parser {
                         line,
                         column: col,
                         message: format!("Expected {:?}, found {:?}", expected, tok),
+                        file: None,
+                        suggestion: (expected == Token::Colon).then(|| ":".to_string()),
                     })
                 }
             }
@@ -201,97 +695,355 @@ impl Parser {
                     line,
                     column: col,
                     message: format!("Expected {:?}, found end of file", expected),
+                    file: None,
+                    suggestion: (expected == Token::Colon).then(|| ":".to_string()),
+                })
+            }
+        }
+    }
+    
+    /// Consume the name of a declaration; a reserved word in its place gets a
+    /// suggested replacement
+    fn expect_name(&mut self, what: &str) -> Result<String> {
+        match self.peek().cloned() {
+            Some(Token::Identifier(name)) => {
+                self.next();
+                Ok(name)
+            }
+            Some(token) if token.keyword().is_some() => {
+                let keyword = token.keyword().unwrap_or_default();
+                let (line, column) = self.current_position();
+                Err(CompileError::Parse {
+                    line,
+                    column,
+                    message: format!("`{}` is a reserved word and cannot be used as a {} name", keyword, what),
+                    file: None,
+                    suggestion: Some(format!("{}_{}", keyword, what)),
                 })
             }
+            _ => Err(self.error_here(format!("Expected {} name", what))),
+        }
+    }
+    
+    /// Limit how deeply guards may nest parentheses and operators
+    pub fn with_max_guard_depth(mut self, depth: usize) -> Self {
+        self.max_guard_depth = depth;
+        self
+    }
+    
+    /// Record `error` and skip to the next declaration keyword.
+    ///
+    /// Returns false when the error cap is reached and parsing should stop.
+    fn recover(&mut self, error: CompileError, start: usize) -> bool {
+        if self.errors.len() >= self.max_errors.unwrap_or(0) {
+            self.suppressed = true;
+            return false;
+        }
+        self.errors.push(error);
+        if self.pos == start {
+            self.next();
+        }
+        while let Some(token) = self.peek() {
+            if matches!(token, Token::Context | Token::Intent | Token::Constraints | Token::Signal | Token::Coil | Token::Rung | Token::Block | Token::Abstract | Token::Network) {
+                break;
+            }
+            self.next();
         }
+        true
     }
     
     pub fn parse_module(&mut self) -> Result<Module> {
+        if let Some(err) = self.lex_error.take() {
+            // The lexer skips the bad text, so report it and parse the tokens as usual
+            match self.max_errors {
+                None => return Err(err),
+                Some(max_errors) if self.errors.len() >= max_errors => self.suppressed = true,
+                Some(_) => self.errors.push(err),
+            }
+        }
+        let is_library = self.peek() == Some(&Token::Library);
+        if is_library {
+            self.next();
+        }
         self.expect(Token::Module)?;
-        let name = match self.next() {
-            Some(Token::Identifier(name)) => name,
-            _ => return Err(CompileError::Parse {
-                line: 1,
-                column: 1,
-                message: "Expected module name".to_string(),
-            }),
+        let name = match self.peek().cloned() {
+            Some(Token::Identifier(name)) => {
+                self.next();
+                name
+            }
+            _ => return Err(self.error_here("Expected module name (an identifier)")),
         };
         
-        let mut context = None;
-        let intent = None;
-        let constraints = None;
-        let mut signals = Vec::new();
-        let mut coils = Vec::new();
-        let mut rungs = Vec::new();
-        let mut blocks = Vec::new();
-        let mut networks = Vec::new();
+        let mut module = Module {
+            name,
+            is_library,
+            context: None,
+            intent: None,
+            constraints: None,
+            signals: Vec::new(),
+            coils: Vec::new(),
+            rungs: Vec::new(),
+            blocks: Vec::new(),
+            networks: Vec::new(),
+        };
         
-        while let Some(token) = self.peek() {
-            match token {
-                Token::Context => {
-                    self.next();
-                    self.expect(Token::Colon)?;
-                    if let Some(Token::String(s)) = self.next() {
-                        context = Some(s);
-                    }
+        while let Some(token) = self.peek().cloned() {
+            let start = self.pos;
+            let error = match self.parse_declaration(&mut module) {
+                Ok(true) => continue,
+                Ok(false) if self.max_errors.is_none() => break,
+                Ok(false) => self.error_here(format!("Expected a declaration, found {:?}", token)),
+                Err(e) if self.max_errors.is_none() => return Err(e),
+                Err(e) => e,
+            };
+            if !self.recover(error, start) {
+                break;
+            }
+        }
+        
+        Ok(module)
+    }
+    
+    /// Parse one top-level declaration into `module`.
+    /// Returns false without consuming anything if no declaration starts here.
+    fn parse_declaration(&mut self, module: &mut Module) -> Result<bool> {
+        let mut feature = None;
+        let mut deprecated = None;
+        loop {
+            match self.peek() {
+                Some(Token::AttributeStart) if feature.is_none() => feature = Some(self.parse_feature_gate()?),
+                Some(Token::At) if deprecated.is_none() => deprecated = Some(self.parse_deprecated()?),
+                Some(Token::AttributeStart | Token::At) => {
+                    return Err(self.error_here("Attribute is given more than once"));
+                }
+                _ => break,
+            }
+        }
+        if deprecated.is_some() && !matches!(self.peek(), Some(Token::Signal | Token::Coil | Token::Block | Token::Abstract)) {
+            return Err(self.error_here("`@deprecated` must be followed by a signal, coil or block"));
+        }
+        match self.peek() {
+            Some(Token::Signal) => {
+                let mut signal = self.parse_signal()?;
+                signal.feature = feature;
+                signal.deprecated = deprecated;
+                module.signals.push(signal);
+            }
+            Some(Token::Coil) => {
+                let mut coil = self.parse_coil()?;
+                coil.feature = feature;
+                coil.deprecated = deprecated;
+                module.coils.push(coil);
+            }
+            Some(Token::Rung) => {
+                let mut rung = self.parse_rung()?;
+                rung.feature = feature;
+                module.rungs.push(rung);
+            }
+            Some(Token::Block | Token::Abstract) => {
+                let mut block = self.parse_block()?;
+                block.feature = feature;
+                block.deprecated = deprecated;
+                module.blocks.push(block);
+            }
+            Some(Token::Network) => {
+                let mut network = self.parse_network()?;
+                network.feature = feature;
+                module.networks.push(network);
+            }
+            _ if feature.is_some() => {
+                return Err(self.error_here(
+                    "A feature gate must be followed by a signal, coil, rung, block or network"
+                ));
+            }
+            Some(Token::Context) => {
+                self.next();
+                self.expect(Token::Colon)?;
+                if let Some(Token::String(s)) = self.next() {
+                    module.context = Some(s);
                 }
-                Token::Signal => {
-                    signals.push(self.parse_signal()?);
+            }
+            Some(Token::Intent) => {
+                if module.intent.is_some() {
+                    return Err(self.error_here(format!("`intent` is declared more than once in module {}", module.name)));
                 }
-                Token::Coil => {
-                    coils.push(self.parse_coil()?);
+                module.intent = Some(self.parse_intent()?);
+            }
+            Some(Token::Constraints) => {
+                if module.constraints.is_some() {
+                    return Err(self.error_here(format!("`constraints` is declared more than once in module {}", module.name)));
                 }
-                Token::Rung => {
-                    rungs.push(self.parse_rung()?);
+                module.constraints = Some(self.parse_constraints()?);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+    
+    /// Parse `intent: goal: "..."`
+    fn parse_intent(&mut self) -> Result<Intent> {
+        self.expect(Token::Intent)?;
+        self.expect(Token::Colon)?;
+        self.expect_key("goal")?;
+        let goal = self.expect_string("goal")?;
+        Ok(Intent { goal: Some(goal) })
+    }
+    
+    /// Parse a `constraints:` block of `data_privacy:`, `quality:` and `cost:`
+    /// sections, each holding `key: value` entries
+    fn parse_constraints(&mut self) -> Result<Constraints> {
+        self.expect(Token::Constraints)?;
+        self.expect(Token::Colon)?;
+        let mut constraints = Constraints {
+            data_privacy: None,
+            quality: None,
+            cost: None,
+        };
+        while let Some(Token::Identifier(section)) = self.peek().cloned() {
+            match section.as_str() {
+                "data_privacy" => {
+                    self.expect_key("data_privacy")?;
+                    let mut privacy = DataPrivacy { jurisdiction: None, pii_handling: None };
+                    while let Some(key) = self.next_key(&["jurisdiction", "pii_handling"])? {
+                        let value = Some(self.expect_string(key)?);
+                        match key {
+                            "jurisdiction" => privacy.jurisdiction = value,
+                            _ => privacy.pii_handling = value,
+                        }
+                    }
+                    constraints.data_privacy = Some(privacy);
                 }
-                Token::Block => {
-                    blocks.push(self.parse_block()?);
+                "quality" => {
+                    self.expect_key("quality")?;
+                    let mut quality = Quality { min_precision: None, min_recall: None };
+                    while let Some(key) = self.next_key(&["min_precision", "min_recall"])? {
+                        let value = Some(self.expect_fraction(key)?);
+                        match key {
+                            "min_precision" => quality.min_precision = value,
+                            _ => quality.min_recall = value,
+                        }
+                    }
+                    constraints.quality = Some(quality);
                 }
-                Token::Network => {
-                    networks.push(self.parse_network()?);
+                "cost" => {
+                    self.expect_key("cost")?;
+                    let mut cost = Cost { max_cost_per_submission: None };
+                    while let Some(key) = self.next_key(&["max_cost_per_submission"])? {
+                        cost.max_cost_per_submission = Some(match self.peek().cloned() {
+                            Some(Token::Number(n)) => {
+                                self.next();
+                                n.to_string()
+                            }
+                            _ => self.expect_string(key)?,
+                        });
+                    }
+                    constraints.cost = Some(cost);
                 }
-                _ => break,
+                _ => return Err(self.error_here(format!(
+                    "Unknown constraints section `{}` (expected data_privacy, quality or cost)", section
+                ))),
             }
         }
-        
-        Ok(Module {
-            name,
-            context,
-            intent,
-            constraints,
-            signals,
-            coils,
-            rungs,
-            blocks,
-            networks,
-        })
+        Ok(constraints)
+    }
+    
+    /// Consume `key:` when the next identifier is one of `keys`
+    fn next_key(&mut self, keys: &[&'static str]) -> Result<Option<&'static str>> {
+        let Some(Token::Identifier(word)) = self.peek() else {
+            return Ok(None);
+        };
+        let Some(key) = keys.iter().find(|key| *key == word).copied() else {
+            return Ok(None);
+        };
+        self.expect_key(key)?;
+        Ok(Some(key))
+    }
+    
+    /// Consume a number between 0 and 1 given as the value of `key`
+    fn expect_fraction(&mut self, key: &str) -> Result<f64> {
+        match self.peek().cloned() {
+            Some(Token::Number(n)) if (0.0..=1.0).contains(&n) => {
+                self.next();
+                Ok(n)
+            }
+            Some(Token::Number(n)) => Err(self.error_here(format!("`{}` must be between 0 and 1, found {}", key, n))),
+            _ => Err(self.error_here(format!("Expected a number for `{}`", key))),
+        }
+    }
+    
+    /// Consume `key:`
+    fn expect_key(&mut self, key: &str) -> Result<()> {
+        if !matches!(self.peek(), Some(Token::Identifier(word)) if word == key) {
+            return Err(self.error_here(format!("Expected `{}:`", key)));
+        }
+        self.next();
+        self.expect(Token::Colon)?;
+        Ok(())
+    }
+    
+    /// Consume a string literal given as the value of `key`
+    fn expect_string(&mut self, key: &str) -> Result<String> {
+        match self.peek().cloned() {
+            Some(Token::String(value)) => {
+                self.next();
+                Ok(value)
+            }
+            _ => Err(self.error_here(format!("Expected a string value for `{}`", key))),
+        }
+    }
+    
+    /// Parse `#[if feature("name")]`, returning the feature name
+    fn parse_feature_gate(&mut self) -> Result<String> {
+        self.expect(Token::AttributeStart)?;
+        let expected = "Expected `if feature(\"name\")` in attribute";
+        for keyword in ["if", "feature"] {
+            if !matches!(self.peek(), Some(Token::Identifier(word)) if word == keyword) {
+                return Err(self.error_here(expected));
+            }
+            self.next();
+        }
+        self.expect(Token::LParen)?;
+        let feature = match self.peek().cloned() {
+            Some(Token::String(feature)) => {
+                self.next();
+                feature
+            }
+            _ => return Err(self.error_here(expected)),
+        };
+        self.expect(Token::RParen)?;
+        self.expect(Token::RBracket)?;
+        Ok(feature)
+    }
+    
+    /// Parse `@deprecated` or `@deprecated("message")`, returning the message
+    fn parse_deprecated(&mut self) -> Result<String> {
+        self.expect(Token::At)?;
+        if !matches!(self.peek(), Some(Token::Identifier(word)) if word == "deprecated") {
+            return Err(self.error_here("Expected `deprecated` after `@`"));
+        }
+        self.next();
+        if self.peek() != Some(&Token::LParen) {
+            return Ok(String::new());
+        }
+        self.next();
+        let message = match self.peek().cloned() {
+            Some(Token::String(message)) => {
+                self.next();
+                message
+            }
+            _ => return Err(self.error_here("Expected a message string in `@deprecated(...)`")),
+        };
+        self.expect(Token::RParen)?;
+        Ok(message)
     }
     
     fn parse_signal(&mut self) -> Result<SignalDecl> {
         self.expect(Token::Signal)?;
-        let name = match self.next() {
-            Some(Token::Identifier(name)) => name,
-            _ => return Err(CompileError::Parse {
-                line: 1,
-                column: 1,
-                message: "Expected signal name".to_string(),
-            }),
-        };
+        let name = self.expect_name("signal")?;
         
         let mut parameters = Vec::new();
         if self.peek() == Some(&Token::LParen) {
-            self.next();
-            while self.peek() != Some(&Token::RParen) {
-                if let Some(Token::Identifier(param)) = self.next() {
-                    parameters.push(param);
-                }
-                if self.peek() == Some(&Token::Comma) {
-                    self.next();
-                } else {
-                    break;
-                }
-            }
-            self.expect(Token::RParen)?;
+            parameters = self.parse_parameters()?;
         }
         
         let mut type_ = None;
@@ -302,53 +1054,82 @@ impl Parser {
             }
         }
         
+        let unit = self.parse_unit()?;
+        
         Ok(SignalDecl {
             name,
             parameters,
             type_,
+            unit,
+            feature: None,
+            deprecated: None,
         })
     }
     
+    /// Parse an optional `unit: "<name>"` annotation
+    fn parse_unit(&mut self) -> Result<Option<String>> {
+        if !matches!(self.peek(), Some(Token::Identifier(s)) if s == "unit") {
+            return Ok(None);
+        }
+        self.next();
+        self.expect(Token::Colon)?;
+        match self.next() {
+            Some(Token::String(unit)) => Ok(Some(unit)),
+            _ => Err(self.error_here("Expected a quoted unit after `unit:`")),
+        }
+    }
+    
     fn parse_coil(&mut self) -> Result<CoilDecl> {
         self.expect(Token::Coil)?;
-        let name = match self.next() {
-            Some(Token::Identifier(name)) => name,
-            _ => return Err(CompileError::Parse {
-                line: 1,
-                column: 1,
-                message: "Expected coil name".to_string(),
-            }),
-        };
+        let name = self.expect_name("coil")?;
         
         let mut parameters = Vec::new();
         if self.peek() == Some(&Token::LParen) {
-            self.next();
-            while self.peek() != Some(&Token::RParen) {
-                if let Some(Token::Identifier(param)) = self.next() {
-                    parameters.push(param);
-                }
-                if self.peek() == Some(&Token::Comma) {
-                    self.next();
-                } else {
-                    break;
-                }
-            }
-            self.expect(Token::RParen)?;
+            parameters = self.parse_parameters()?;
         }
         
         let mut latching = None;
         let mut critical = None;
+        let mut unit = None;
         
-        // Parse optional modifiers (simplified - would need more parsing)
-        while let Some(token) = self.peek() {
-            match token {
-                Token::Identifier(ref s) if s == "latching" => {
-                    self.next();
-                    latching = Some(true);
+        // Optional `[latching, critical]` modifiers and `unit: "..."`
+        loop {
+            match self.peek() {
+                Some(Token::Identifier(s)) if s == "unit" => {
+                    unit = self.parse_unit()?;
                 }
-                Token::Identifier(ref s) if s == "critical" => {
+                Some(Token::LBracket) => {
                     self.next();
-                    critical = Some(true);
+                    loop {
+                        match self.peek().cloned() {
+                            Some(Token::Identifier(modifier)) if modifier == "latching" => latching = Some(true),
+                            Some(Token::Identifier(modifier)) if modifier == "critical" => critical = Some(true),
+                            Some(Token::Identifier(modifier)) => {
+                                return Err(self.error_here(format!(
+                                    "Unknown coil modifier `{}` (expected latching or critical)", modifier
+                                )));
+                            }
+                            _ => return Err(self.error_here("Expected a coil modifier (latching or critical)")),
+                        }
+                        self.next();
+                        if self.peek() != Some(&Token::Comma) {
+                            break;
+                        }
+                        self.next();
+                    }
+                    self.expect(Token::RBracket)?;
+                }
+                // The old bare spelling would otherwise surface as a confusing "expected a declaration"
+                Some(Token::Identifier(s)) if s == "latching" || s == "critical" => {
+                    let modifier = s.clone();
+                    let (line, column) = self.current_position();
+                    return Err(CompileError::Parse {
+                        line,
+                        column,
+                        message: format!("Coil modifiers go in brackets: `coil {} [{}]`", name, modifier),
+                        file: None,
+                        suggestion: Some(format!("[{}]", modifier)),
+                    });
                 }
                 _ => break,
             }
@@ -359,41 +1140,68 @@ impl Parser {
             parameters,
             latching,
             critical,
+            unit,
+            feature: None,
+            deprecated: None,
         })
     }
     
     fn parse_rung(&mut self) -> Result<RungDecl> {
         self.expect(Token::Rung)?;
-        let name = match self.next() {
-            Some(Token::Identifier(name)) => name,
-            _ => return Err(CompileError::Parse {
-                line: 1,
-                column: 1,
-                message: "Expected rung name".to_string(),
-            }),
-        };
+        let name = self.expect_name("rung")?;
         self.expect(Token::Colon)?;
         self.expect(Token::When)?;
         let guard = self.parse_guard()?;
-        self.expect(Token::Then)?;
+        if self.peek() != Some(&Token::Then) {
+            return Err(self.error_here(format!("Expected `then` after the guard of rung {}", name)));
+        }
+        self.next();
         let actions = self.parse_actions()?;
         
+        let mut else_actions = Vec::new();
+        if self.peek() == Some(&Token::Else) {
+            if actions.is_empty() {
+                return Err(self.error_here(format!("Expected an action after `then` in rung {}", name)));
+            }
+            self.next();
+            else_actions = self.parse_actions()?;
+            if else_actions.is_empty() {
+                return Err(self.error_here(format!("Expected an action after `else` in rung {}", name)));
+            }
+        }
+        
+        // An optional `end` closes the rung explicitly
+        if self.peek() == Some(&Token::End) {
+            if actions.is_empty() {
+                return Err(self.error_here(format!("Expected an action after `then` in rung {}", name)));
+            }
+            self.next();
+        }
+        
         Ok(RungDecl {
             name,
             guard,
             actions,
+            else_actions,
+            feature: None,
         })
     }
     
+    /// Parse a guard expression.
+    ///
+    /// Precedence from loosest to tightest is `OR`, `AND`, `NOT`. `NOT` applies
+    /// only to the primary that follows it, so `NOT a AND b` is `(NOT a) AND b`.
     fn parse_guard(&mut self) -> Result<GuardExpr> {
         self.parse_guard_or()
     }
     
     fn parse_guard_or(&mut self) -> Result<GuardExpr> {
         let mut left = self.parse_guard_and()?;
+        let mut height = guard_height(&left);
         while self.peek() == Some(&Token::Or) {
             self.next();
             let right = self.parse_guard_and()?;
+            height = self.check_guard_height(height.max(guard_height(&right)) + 1)?;
             left = GuardExpr::Or {
                 left: Box::new(left),
                 right: Box::new(right),
@@ -404,9 +1212,11 @@ impl Parser {
     
     fn parse_guard_and(&mut self) -> Result<GuardExpr> {
         let mut left = self.parse_guard_not()?;
+        let mut height = guard_height(&left);
         while self.peek() == Some(&Token::And) {
             self.next();
             let right = self.parse_guard_not()?;
+            height = self.check_guard_height(height.max(guard_height(&right)) + 1)?;
             left = GuardExpr::And {
                 left: Box::new(left),
                 right: Box::new(right),
@@ -417,8 +1227,17 @@ impl Parser {
     
     fn parse_guard_not(&mut self) -> Result<GuardExpr> {
         if self.peek() == Some(&Token::Not) {
+            let (line, column) = self.tokens.get(self.pos).map(|(_, l, c)| (*l, *c)).unwrap_or((1, 1));
             self.next();
+            let parenthesized = self.peek() == Some(&Token::LParen);
             let expr = self.parse_guard_primary()?;
+            self.check_guard_height(guard_height(&expr) + 1)?;
+            if !parenthesized && matches!(self.peek(), Some(Token::And) | Some(Token::Or)) {
+                self.warnings.push(Diagnostic::warning(
+                    "`NOT` applies only to the contact that follows it; \
+                     add parentheses to make the intended grouping explicit"
+                ).at(line, column));
+            }
             Ok(GuardExpr::Not {
                 expr: Box::new(expr),
             })
@@ -427,187 +1246,483 @@ impl Parser {
         }
     }
     
+    /// Reject a guard whose operators nest deeper than the limit, since later
+    /// passes recurse over the tree; returns `height` when it is within it
+    fn check_guard_height(&self, height: usize) -> Result<usize> {
+        if height > self.max_guard_depth {
+            return Err(self.error_here(format!("guard nesting too deep (limit {})", self.max_guard_depth)));
+        }
+        Ok(height)
+    }
+    
     fn parse_guard_primary(&mut self) -> Result<GuardExpr> {
         if self.peek() == Some(&Token::LParen) {
+            // Each level recurses, so bound the depth rather than overflow the stack
+            if self.guard_depth >= self.max_guard_depth {
+                return Err(self.error_here(format!("guard nesting too deep (limit {})", self.max_guard_depth)));
+            }
             self.next();
-            let expr = self.parse_guard()?;
+            self.guard_depth += 1;
+            let expr = self.parse_guard();
+            self.guard_depth -= 1;
+            let expr = expr?;
             self.expect(Token::RParen)?;
             Ok(expr)
-        } else if self.peek() == Some(&Token::NO) || self.peek() == Some(&Token::NC) {
+        } else if matches!(self.peek(), Some(Token::NO | Token::NC | Token::P | Token::N)) {
             let contact_type = match self.next() {
                 Some(Token::NO) => ContactType::NO,
                 Some(Token::NC) => ContactType::NC,
+                Some(Token::P) => ContactType::RisingEdge,
+                Some(Token::N) => ContactType::FallingEdge,
                 _ => unreachable!(),
             };
-            let name = match self.next() {
-                Some(Token::Identifier(name)) => name,
-                _ => return Err(CompileError::Parse {
-                    line: 1,
-                    column: 1,
-                    message: "Expected signal/coil name after NO/NC".to_string(),
-                }),
+            let name = match self.peek().cloned() {
+                Some(Token::Identifier(name)) => {
+                    self.next();
+                    name
+                }
+                _ => return Err(self.error_here(format!("Expected signal/coil name after {}", contact_type.symbol()))),
             };
             
             let mut arguments = Vec::new();
             if self.peek() == Some(&Token::LParen) {
-                self.next();
-                while self.peek() != Some(&Token::RParen) {
-                    arguments.push(self.parse_expr()?);
-                    if self.peek() == Some(&Token::Comma) {
-                        self.next();
-                    } else {
-                        break;
-                    }
-                }
-                self.expect(Token::RParen)?;
+                arguments = self.parse_arguments()?;
             }
             
             Ok(GuardExpr::Contact {
                 name,
                 contact_type,
                 arguments,
+                kind: None,
             })
         } else {
-            // Bare identifier (treated as NO contact)
-            let name = match self.next() {
-                Some(Token::Identifier(name)) => name,
-                _ => return Err(CompileError::Parse {
-                    line: 1,
-                    column: 1,
-                    message: "Expected contact or identifier".to_string(),
-                }),
+            // Bare identifier (treated as NO contact), or the left side of a comparison
+            let name = match self.peek().cloned() {
+                Some(Token::Identifier(name)) => {
+                    self.next();
+                    name
+                }
+                _ => return Err(self.error_here("Expected contact or identifier")),
             };
+            let op = match self.peek() {
+                Some(Token::Greater) => Some(CompareOp::Gt),
+                Some(Token::Less) => Some(CompareOp::Lt),
+                Some(Token::GreaterEq) => Some(CompareOp::Ge),
+                Some(Token::LessEq) => Some(CompareOp::Le),
+                Some(Token::EqEq) => Some(CompareOp::Eq),
+                Some(Token::NotEq) => Some(CompareOp::Ne),
+                _ => None,
+            };
+            if let Some(op) = op {
+                self.next();
+                return Ok(GuardExpr::Compare {
+                    left: Expr::Identifier(name),
+                    op,
+                    right: self.parse_expr()?,
+                });
+            }
             Ok(GuardExpr::Contact {
                 name,
                 contact_type: ContactType::NO,
                 arguments: Vec::new(),
+                kind: None,
             })
         }
     }
     
     fn parse_expr(&mut self) -> Result<Expr> {
+        let (line, column) = self.current_position();
         match self.next() {
+            // A single leading minus negates a number literal
+            Some(Token::Minus) => match self.peek() {
+                Some(Token::Number(n)) => {
+                    let n = *n;
+                    self.next();
+                    Ok(Expr::Number(-n))
+                }
+                _ => Err(CompileError::Parse {
+                    line,
+                    column,
+                    message: "Expected a number after '-'".to_string(),
+                    file: None,
+                    suggestion: None,
+                }),
+            },
             Some(Token::String(s)) => Ok(Expr::String(s)),
             Some(Token::Number(n)) => Ok(Expr::Number(n)),
             Some(Token::True) => Ok(Expr::Boolean(true)),
             Some(Token::False) => Ok(Expr::Boolean(false)),
             Some(Token::Identifier(name)) => Ok(Expr::Identifier(name)),
-            _ => Err(CompileError::Parse {
-                line: 1,
-                column: 1,
-                message: "Expected expression".to_string(),
+            Some(token) => Err(CompileError::Parse {
+                line,
+                column,
+                message: format!("Expected expression, found {:?}", token),
+                file: None,
+                suggestion: None,
+            }),
+            None => Err(CompileError::Parse {
+                line,
+                column,
+                message: "Expected expression, found end of input".to_string(),
+                file: None,
+                suggestion: None,
             }),
         }
     }
     
+    /// Parse a run of actions, optionally separated by `,` or `and`
     fn parse_actions(&mut self) -> Result<Vec<Action>> {
         let mut actions = Vec::new();
+        let mut separated = false;
         loop {
             let action = match self.peek() {
                 Some(Token::Energise) => {
                     self.next();
-                    let coil = match self.next() {
-                        Some(Token::Identifier(name)) => name,
-                        _ => return Err(CompileError::Parse {
-                            line: 1,
-                            column: 1,
-                            message: "Expected coil name".to_string(),
-                        }),
-                    };
+                    let coil = self.expect_name("coil")?;
                     let mut arguments = Vec::new();
                     if self.peek() == Some(&Token::LParen) {
+                        arguments = self.parse_arguments()?;
+                    }
+                    let mut value = None;
+                    if self.peek() == Some(&Token::Equals) {
                         self.next();
-                        while self.peek() != Some(&Token::RParen) {
-                            arguments.push(self.parse_expr()?);
-                            if self.peek() == Some(&Token::Comma) {
-                                self.next();
-                            } else {
-                                break;
-                            }
-                        }
-                        self.expect(Token::RParen)?;
+                        value = Some(self.parse_expr()?);
                     }
                     Action {
                         action_type: ActionType::Energise,
                         coil,
                         arguments,
+                        value,
+                        condition: None,
                     }
                 }
                 Some(Token::DeEnergise) => {
                     self.next();
-                    let coil = match self.next() {
-                        Some(Token::Identifier(name)) => name,
-                        _ => return Err(CompileError::Parse {
-                            line: 1,
-                            column: 1,
-                            message: "Expected coil name".to_string(),
-                        }),
-                    };
+                    let coil = self.expect_name("coil")?;
                     Action {
                         action_type: ActionType::DeEnergise,
                         coil,
                         arguments: Vec::new(),
+                        value: None,
+                        condition: None,
                     }
                 }
+                Some(Token::Set | Token::Reset) => {
+                    let action_type = match self.next() {
+                        Some(Token::Set) => ActionType::Set,
+                        _ => ActionType::Reset,
+                    };
+                    let coil = self.expect_name("coil")?;
+                    Action {
+                        action_type,
+                        coil,
+                        arguments: Vec::new(),
+                        value: None,
+                        condition: None,
+                    }
+                }
+                Some(Token::Escalate) => {
+                    self.next();
+                    let coil = self.expect_name("coil")?;
+                    let mut arguments = Vec::new();
+                    if self.peek() == Some(&Token::LParen) {
+                        arguments = self.parse_arguments()?;
+                    }
+                    Action {
+                        action_type: ActionType::Escalate,
+                        coil,
+                        arguments,
+                        value: None,
+                        condition: None,
+                    }
+                }
+                Some(Token::Require) => {
+                    self.next();
+                    // `require name(args)` names a precondition; anything else is a guard
+                    if let Some(Token::Identifier(name)) = self.peek().cloned().filter(|_| !self.name_continues_guard()) {
+                        self.next();
+                        let mut arguments = Vec::new();
+                        if self.peek() == Some(&Token::LParen) {
+                            arguments = self.parse_arguments()?;
+                        }
+                        Action {
+                            action_type: ActionType::Require,
+                            coil: name,
+                            arguments,
+                            value: None,
+                            condition: None,
+                        }
+                    } else {
+                        Action {
+                            action_type: ActionType::Require,
+                            coil: String::new(),
+                            arguments: Vec::new(),
+                            value: None,
+                            condition: Some(self.parse_guard()?),
+                        }
+                    }
+                }
+                _ if separated => return Err(self.error_here("Expected an action after separator")),
                 _ => break,
             };
             actions.push(action);
+            
+            separated = matches!(self.peek(), Some(Token::Comma))
+                || matches!(self.peek(), Some(Token::Identifier(word)) if word == "and");
+            if separated {
+                self.next();
+            }
         }
         Ok(actions)
     }
     
+    /// Whether the identifier at the cursor, with any argument list, is
+    /// followed by a guard operator rather than ending a named target
+    fn name_continues_guard(&self) -> bool {
+        let mut index = self.pos + 1;
+        if matches!(self.tokens.get(index), Some((Token::LParen, _, _))) {
+            let mut depth = 0;
+            while let Some((token, _, _)) = self.tokens.get(index) {
+                index += 1;
+                match token {
+                    Token::LParen => depth += 1,
+                    Token::RParen if depth == 1 => break,
+                    Token::RParen => depth -= 1,
+                    _ => {}
+                }
+            }
+        }
+        matches!(
+            self.tokens.get(index).map(|(token, _, _)| token),
+            Some(Token::And | Token::Or | Token::Greater | Token::Less | Token::GreaterEq
+                | Token::LessEq | Token::EqEq | Token::NotEq)
+        )
+    }
+    
     fn parse_block(&mut self) -> Result<BlockDecl> {
+        let (line, column) = self.current_position();
+        let is_abstract = self.peek() == Some(&Token::Abstract);
+        if is_abstract {
+            self.next();
+        }
         self.expect(Token::Block)?;
-        let name = match self.next() {
-            Some(Token::Identifier(name)) => name,
-            _ => return Err(CompileError::Parse {
-                line: 1,
-                column: 1,
-                message: "Expected block name".to_string(),
-            }),
-        };
+        let name = self.expect_name("block")?;
         self.expect(Token::Colon)?;
         
-        // Simplified block parsing - would need full implementation
-        Ok(BlockDecl {
+        let mut block = BlockDecl {
             name,
+            position: Some(Position { line, column }),
+            is_abstract,
             inputs: Vec::new(),
             outputs: Vec::new(),
             internals: Vec::new(),
             implementation: None,
             effect: None,
-        })
+            feature: None,
+            deprecated: None,
+        };
+        if self.peek() == Some(&Token::LBrace) {
+            self.parse_block_body(&mut block)?;
+        }
+        Ok(block)
+    }
+    
+    /// Parse a block body in braces:
+    ///
+    /// ```text
+    /// { inputs: raw: real, enable: bool
+    ///   outputs: smoothed: real
+    ///   internals: sum: real
+    ///   implementation: "filters:smooth"
+    ///   effect: "pure" }
+    /// ```
+    fn parse_block_body(&mut self, block: &mut BlockDecl) -> Result<()> {
+        self.expect(Token::LBrace)?;
+        loop {
+            match self.peek() {
+                Some(Token::Inputs) => {
+                    self.next();
+                    self.expect(Token::Colon)?;
+                    block.inputs.extend(self.parse_ports()?.into_iter().map(|(name, type_)| PortDecl { name, type_ }));
+                }
+                Some(Token::Outputs) => {
+                    self.next();
+                    self.expect(Token::Colon)?;
+                    block.outputs.extend(self.parse_ports()?.into_iter().map(|(name, type_)| PortDecl { name, type_ }));
+                }
+                Some(Token::Internals) => {
+                    self.next();
+                    self.expect(Token::Colon)?;
+                    block.internals.extend(self.parse_ports()?.into_iter().map(|(name, type_)| InternalDecl { name, type_ }));
+                }
+                Some(Token::Implementation) => {
+                    if block.implementation.is_some() {
+                        return Err(self.error_here(format!("`implementation` is given more than once in block {}", block.name)));
+                    }
+                    self.next();
+                    self.expect(Token::Colon)?;
+                    block.implementation = Some(self.expect_string("implementation")?);
+                }
+                Some(Token::Effect) => {
+                    if block.effect.is_some() {
+                        return Err(self.error_here(format!("`effect` is given more than once in block {}", block.name)));
+                    }
+                    self.next();
+                    self.expect(Token::Colon)?;
+                    block.effect = Some(self.expect_string("effect")?);
+                }
+                Some(Token::RBrace) => break,
+                _ => return Err(self.error_here(format!(
+                    "Expected `inputs`, `outputs`, `internals`, `implementation`, `effect` or `}}` in block {}", block.name
+                ))),
+            }
+        }
+        self.expect(Token::RBrace)?;
+        Ok(())
+    }
+    
+    /// Parse `name: type` declarations separated by commas or newlines
+    fn parse_ports(&mut self) -> Result<Vec<(String, String)>> {
+        let mut ports = Vec::new();
+        while let Some(Token::Identifier(name)) = self.peek().cloned() {
+            self.next();
+            self.expect(Token::Colon)?;
+            let type_ = match self.peek().cloned() {
+                Some(Token::Identifier(type_)) => {
+                    self.next();
+                    type_
+                }
+                _ => return Err(self.error_here(format!("Port `{}` is missing a type after `:`", name))),
+            };
+            ports.push((name, type_));
+            if self.peek() == Some(&Token::Comma) {
+                self.next();
+            }
+        }
+        Ok(ports)
     }
     
     fn parse_network(&mut self) -> Result<NetworkDecl> {
         self.expect(Token::Network)?;
-        let name = match self.next() {
-            Some(Token::Identifier(name)) => name,
-            _ => return Err(CompileError::Parse {
-                line: 1,
-                column: 1,
-                message: "Expected network name".to_string(),
-            }),
-        };
+        let name = self.expect_name("network")?;
         self.expect(Token::Colon)?;
         
-        // Simplified network parsing
-        Ok(NetworkDecl {
+        let mut network = NetworkDecl {
             name,
             wires: Vec::new(),
             outputs: Vec::new(),
-        })
+            feature: None,
+        };
+        if self.peek() == Some(&Token::LBrace) {
+            self.parse_network_body(&mut network)?;
+        }
+        Ok(network)
+    }
+    
+    /// Parse a network body in braces:
+    ///
+    /// ```text
+    /// { wires:
+    ///     sensor -> filter_in
+    ///     filter_out -> alarm_in
+    ///   outputs:
+    ///     alarm = alarm_out }
+    /// ```
+    fn parse_network_body(&mut self, network: &mut NetworkDecl) -> Result<()> {
+        self.expect(Token::LBrace)?;
+        loop {
+            match self.peek() {
+                Some(Token::Wires) => {
+                    self.next();
+                    self.expect(Token::Colon)?;
+                    while let Some(Token::Identifier(source)) = self.peek().cloned() {
+                        let (line, column) = self.current_position();
+                        self.next();
+                        if self.peek() != Some(&Token::Arrow) {
+                            return Err(self.error_here(format!("Expected `->` after wire source `{}`", source)));
+                        }
+                        self.next();
+                        let target = self.expect_name("wire target")?;
+                        network.wires.push(Wire { source, target, position: Some(Position { line, column }) });
+                        if self.peek() == Some(&Token::Comma) {
+                            self.next();
+                        }
+                    }
+                }
+                Some(Token::Outputs) => {
+                    self.next();
+                    self.expect(Token::Colon)?;
+                    while let Some(Token::Identifier(name)) = self.peek().cloned() {
+                        let (line, column) = self.current_position();
+                        self.next();
+                        self.expect(Token::Equals)?;
+                        let source = self.expect_name("output source")?;
+                        network.outputs.push(Output { name, source, position: Some(Position { line, column }) });
+                        if self.peek() == Some(&Token::Comma) {
+                            self.next();
+                        }
+                    }
+                }
+                Some(Token::RBrace) => break,
+                _ => return Err(self.error_here(format!(
+                    "Expected `wires`, `outputs` or `}}` in network {}", network.name
+                ))),
+            }
+        }
+        self.expect(Token::RBrace)?;
+        Ok(())
     }
 }
 
 pub fn parse(source: &str) -> Result<Module> {
+    parse_with_warnings(source).map(|(module, _)| module)
+}
+
+/// Parse a module, recording `path` in any parse error
+pub fn parse_named(source: &str, path: &str) -> Result<Module> {
+    parse(source).map_err(|e| e.with_file(path))
+}
+
+/// Parse a module, also returning advisory warnings raised while parsing
+pub fn parse_with_warnings(source: &str) -> Result<(Module, Vec<Diagnostic>)> {
+    let mut parser = Parser::new(source);
+    let module = parser.parse_module()?;
+    Ok((module, parser.warnings))
+}
+
+/// Parse a module, recovering at declaration boundaries to report up to
+/// `max_errors` errors alongside any warnings.
+///
+/// The module is returned, missing its broken declarations, whenever the header parsed.
+pub fn parse_all(source: &str, max_errors: usize) -> (Option<Module>, Vec<Diagnostic>) {
     let mut parser = Parser::new(source);
-    parser.parse_module()
+    parser.max_errors = Some(max_errors);
+    let module = match parser.parse_module() {
+        Ok(module) => Some(module),
+        Err(e) => {
+            if parser.errors.len() < max_errors {
+                parser.errors.push(e);
+            } else {
+                parser.suppressed = true;
+            }
+            None
+        }
+    };
+    
+    let mut diagnostics = std::mem::take(&mut parser.warnings);
+    diagnostics.extend(parser.errors.iter().map(Diagnostic::from));
+    if parser.suppressed {
+        diagnostics.push(Diagnostic::note(
+            format!("further errors suppressed after the first {}", max_errors)
+        ));
+    }
+    (module, diagnostics)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::diagnostic::Severity;
 
     #[test]
     fn test_parse_basic_module() {
@@ -629,4 +1744,632 @@ rung test_rung:
         assert_eq!(module.coils.len(), 1);
         assert_eq!(module.rungs.len(), 1);
     }
+
+    #[test]
+    fn test_separated_and_prefixed_numbers() {
+        let numbers = |source: &str| -> Vec<f64> {
+            lex(source).into_iter().filter_map(|(token, _)| match token {
+                Token::Number(n) => Some(n),
+                _ => None,
+            }).collect()
+        };
+        assert_eq!(numbers("1_000 0o17 0o1_7 2_5.0_5"), vec![1000.0, 15.0, 15.0, 25.05]);
+        assert_eq!(numbers("1_000.5 0xFF 0xff_00 0b1010 0b1111_0000"), vec![1000.5, 255.0, 65280.0, 10.0, 240.0]);
+        // A leading underscore makes an identifier, not a number
+        assert_eq!(lex("_1")[0].0, Token::Identifier("_1".to_string()));
+        
+        for literal in ["1__0", "1_", "0o", "0o18", "0x", "0x_", "0xFG", "0b", "0b102"] {
+            let source = format!("module m\nrung r:\n  when NO a\n  then energise c({})", literal);
+            match parse(&source) {
+                Err(CompileError::Parse { message, .. }) => {
+                    assert!(message.starts_with("Malformed number literal"), "{}: {}", literal, message);
+                }
+                other => panic!("expected {} to be rejected, got {:?}", literal, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_all_caps_errors() {
+        let source = format!("module m\nsignal ok\n{}", "coil 5\n".repeat(10));
+        let (module, diagnostics) = parse_all(&source, 3);
+        assert_eq!(module.unwrap().signals.len(), 1);
+        let errors: Vec<_> = diagnostics.iter().filter(|d| d.severity == Severity::Error).collect();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(diagnostics.last().unwrap().severity, Severity::Note);
+        
+        // Recovery resumes at the next declaration
+        let (module, diagnostics) = parse_all("module m\ncoil 5\nsignal ok", 3);
+        assert_eq!(module.unwrap().signals.len(), 1);
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_all_keeps_module_after_lex_error() {
+        let (module, diagnostics) = parse_all("module m\nsignal a\ncoil c\nrung r:\n  when NO a\n  then energise c(0x)", 3);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        let module = module.unwrap();
+        assert_eq!(module.name, "m");
+        assert_eq!(module.rungs.len(), 1);
+    }
+
+    #[test]
+    fn test_action_separators() {
+        let targets = |source: &str| -> Vec<String> {
+            let module = parse(&format!("module m\nrung r:\n  when NO a\n  then {}", source)).unwrap();
+            module.rungs[0].actions.iter().map(|a| a.coil.clone()).collect()
+        };
+        for source in ["energise x\n  energise y", "energise x, energise y", "energise x and de_energise y", "energise x(1), energise y"] {
+            assert_eq!(targets(source), ["x", "y"], "{}", source);
+        }
+        assert!(parse("module m\nrung r:\n  when NO a\n  then energise x,").is_err());
+        assert!(parse("module m\nrung r:\n  when NO a and NO b\n  then energise x").is_err());
+        
+        let module = parse("module m\nrung r:\n  when NO a\n  then require NO a and energise b").unwrap();
+        let actions = &module.rungs[0].actions;
+        assert_eq!(actions[0].condition.as_ref().unwrap().to_string(), "NO a");
+        assert_eq!(actions[1].coil, "b");
+    }
+
+    #[test]
+    fn test_named_parse_error_includes_file() {
+        let error = parse_named("module m\ncoil 5", "plant/pumps.charta").unwrap_err();
+        assert!(matches!(&error, CompileError::Parse { file: Some(f), .. } if f == "plant/pumps.charta"));
+        assert!(error.to_string().starts_with("Parse error in plant/pumps.charta at line"), "{}", error);
+        
+        let error = parse("module m\ncoil 5").unwrap_err();
+        assert!(error.to_string().starts_with("Parse error at line"), "{}", error);
+    }
+
+    #[test]
+    fn test_require_condition() {
+        let module = parse("module m\nrung r:\n  when NO start\n  then require (NO door_closed AND NO e_stop) energise motor").unwrap();
+        let actions = &module.rungs[0].actions;
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].action_type, ActionType::Require);
+        assert_eq!(actions[0].condition.as_ref().unwrap().to_string(), "NO door_closed AND NO e_stop");
+        assert_eq!(actions[1].coil, "motor");
+    }
+
+    #[test]
+    fn test_require_target() {
+        let module = parse("module m\nrung r:\n  when NO start\n  then require operator_ack, energise motor").unwrap();
+        let actions = &module.rungs[0].actions;
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].action_type, ActionType::Require);
+        assert_eq!(actions[0].coil, "operator_ack");
+        assert!(actions[0].condition.is_none());
+    }
+
+    #[test]
+    fn test_require_guard_starting_with_a_name() {
+        for (guard, expected) in [("level > 5", "level > 5"), ("a AND b", "NO a AND NO b")] {
+            let module = parse(&format!("module m\nrung r:\n  when NO start\n  then require {}\n  energise motor", guard)).unwrap();
+            let actions = &module.rungs[0].actions;
+            assert_eq!(actions.len(), 2, "{}", guard);
+            assert_eq!(actions[0].condition.as_ref().unwrap().to_string(), expected);
+            assert_eq!(actions[1].coil, "motor");
+        }
+    }
+
+    #[test]
+    fn test_tokenize_reports_spans_and_errors() {
+        let tokens = tokenize("module m\n  signal a").unwrap();
+        assert_eq!(tokens[0], (Token::Module, Span { start: 0, end: 6, line: 1, column: 1 }));
+        assert_eq!(tokens[2], (Token::Signal, Span { start: 11, end: 17, line: 2, column: 3 }));
+        assert_eq!(tokens.len(), 4);
+        
+        match tokenize("module m\nsignal a $") {
+            Err(CompileError::Parse { line, column, message, .. }) => {
+                assert_eq!((line, column), (2, 10));
+                assert_eq!(message, "Unexpected character `$`");
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert!(matches!(tokenize("x = 0x"), Err(CompileError::Parse { column: 5, .. })));
+    }
+
+    #[test]
+    fn test_relex_range_matches_full_lex() {
+        let before = "module m\nsignal start\ncoil motor\nrung r:\n  when NO start\n  then energise motor\n";
+        let old_tokens = lex(before);
+        let edits = [
+            ("NO start", "NC start AND NO stop"),
+            ("signal start", "signal start_button"),
+            ("energise motor", "de_energise motor // stop"),
+            ("\n  when", "\n\n  when"),
+            ("coil motor", "coil /* motor"),
+            ("signal start", "signal \"start"),
+        ];
+        for (old_text, new_text) in edits {
+            let start = before.find(old_text).unwrap();
+            let after = before.replacen(old_text, new_text, 1);
+            let tokens = relex_range(&after, &old_tokens, start..start + old_text.len(), new_text.len());
+            assert_eq!(tokens, lex(&after), "editing {:?}", old_text);
+        }
+        
+        // Breaking a block comment's close lets it swallow the rest of the file
+        let before = "module m\n/* blk */\nsignal start\n";
+        let start = before.find("*/").unwrap();
+        let after = before.replacen("*/", " /", 1);
+        assert_eq!(relex_range(&after, &lex(before), start..start + 2, 2), lex(&after));
+    }
+
+    #[test]
+    fn test_tokens_at_line() {
+        let source = "module m\nsignal start\nrung r: when NO start\ncoil motor\n";
+        assert_eq!(tokens_at_line(source, 3), vec![
+            (Token::Rung, 1),
+            (Token::Identifier("r".to_string()), 6),
+            (Token::Colon, 7),
+            (Token::When, 9),
+            (Token::NO, 14),
+            (Token::Identifier("start".to_string()), 17),
+        ]);
+        assert!(tokens_at_line(source, 9).is_empty());
+    }
+
+    #[test]
+    fn test_group_breaks() {
+        let source = "module m\n\nsignal a\nsignal b\n\n\n// outputs\ncoil x\ncoil y\n\nrung r:\n  when NO a\n\n  then energise x\nabstract block stub\n\n#[if feature(\"debug\")]\nsignal d\n";
+        // Blank lines inside a rung do not separate declarations; several in a row count once
+        assert_eq!(group_breaks(source), vec![3, 8, 11, 17]);
+    }
+
+    #[test]
+    fn test_columns_point_at_bad_token() {
+        // Tabs count as one column; skipped whitespace and comments still advance it
+        match parse("module m\nrung r:\n\twhen NO a // note\n\tthen  energise  42") {
+            Err(CompileError::Parse { line, column, message, .. }) => {
+                assert_eq!((line, column), (4, 18));
+                assert_eq!(message, "Expected coil name");
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        match parse("module m\nsignal\t\t42") {
+            Err(CompileError::Parse { line, column, .. }) => assert_eq!((line, column), (2, 9)),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_intent() {
+        let module = parse("module m\nintent:\n  goal: \"Keep the tank from overflowing\"\nsignal a").unwrap();
+        assert_eq!(module.intent.unwrap().goal.as_deref(), Some("Keep the tank from overflowing"));
+        assert_eq!(module.signals.len(), 1);
+        
+        let err = parse("module m\nintent: goal: \"a\"\nintent: goal: \"b\"").unwrap_err();
+        assert!(err.to_string().contains("`intent` is declared more than once in module m"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_constraints() {
+        let source = "module m\nconstraints:\n  data_privacy:\n    jurisdiction: \"EU\"\n    pii_handling: \"redact\"\n  quality:\n    min_precision: 0.95\n    min_recall: 0.9\n  cost:\n    max_cost_per_submission: \"0.05 USD\"\nsignal a";
+        let module = parse(source).unwrap();
+        let constraints = module.constraints.unwrap();
+        let privacy = constraints.data_privacy.unwrap();
+        assert_eq!(privacy.jurisdiction.as_deref(), Some("EU"));
+        assert_eq!(privacy.pii_handling.as_deref(), Some("redact"));
+        let quality = constraints.quality.unwrap();
+        assert_eq!((quality.min_precision, quality.min_recall), (Some(0.95), Some(0.9)));
+        assert_eq!(constraints.cost.unwrap().max_cost_per_submission.as_deref(), Some("0.05 USD"));
+        assert_eq!(module.signals.len(), 1);
+        
+        let err = parse("module m\nconstraints:\n  quality:\n    min_recall: 2").unwrap_err();
+        assert!(err.to_string().contains("`min_recall` must be between 0 and 1"), "{}", err);
+        let err = parse("module m\nconstraints:\n  latency:\n").unwrap_err();
+        assert!(err.to_string().contains("Unknown constraints section `latency`"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_block_ports() {
+        let source = "module m\nblock filter: {\n  inputs: raw: real, enable: bool\n  outputs:\n    smoothed: real\n    ready: bool\n  internals: sum: real\n}\nsignal a";
+        let module = parse(source).unwrap();
+        let block = &module.blocks[0];
+        let names = |ports: &[PortDecl]| ports.iter().map(|p| format!("{}: {}", p.name, p.type_)).collect::<Vec<_>>();
+        assert_eq!(names(&block.inputs), ["raw: real", "enable: bool"]);
+        assert_eq!(names(&block.outputs), ["smoothed: real", "ready: bool"]);
+        assert_eq!(block.internals.len(), 1);
+        assert_eq!((block.internals[0].name.as_str(), block.internals[0].type_.as_str()), ("sum", "real"));
+        assert_eq!(module.signals.len(), 1);
+        
+        match parse("module m\nblock filter: {\n  inputs: raw:, enable: bool\n}") {
+            Err(CompileError::Parse { line, column, message, .. }) => {
+                assert_eq!((line, column), (3, 15));
+                assert_eq!(message, "Port `raw` is missing a type after `:`");
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_block_implementation_and_effect() {
+        let module = parse("library module m\nblock score: {\n  outputs: risk: real\n  implementation: \"models.risk:score\"\n  effect: \"pure\"\n}").unwrap();
+        let block = &module.blocks[0];
+        assert_eq!(block.implementation.as_deref(), Some("models.risk:score"));
+        assert_eq!(block.effect.as_deref(), Some("pure"));
+        
+        let err = parse("library module m\nblock b: { effect: \"pure\" effect: \"io\" }").unwrap_err();
+        assert!(err.to_string().contains("`effect` is given more than once in block b"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_network_wires() {
+        let source = "library module m\nnetwork plant: {\n  wires:\n    sensor -> filter_in\n    filter_out -> alarm_in\n  outputs:\n    alarm = alarm_out\n}";
+        let module = parse(source).unwrap();
+        let network = &module.networks[0];
+        let wires: Vec<(&str, &str)> = network.wires.iter().map(|w| (w.source.as_str(), w.target.as_str())).collect();
+        assert_eq!(wires, [("sensor", "filter_in"), ("filter_out", "alarm_in")]);
+        assert_eq!(network.wires[1].position, Some(Position { line: 5, column: 5 }));
+        assert_eq!((network.outputs[0].name.as_str(), network.outputs[0].source.as_str()), ("alarm", "alarm_out"));
+        
+        match parse("library module m\nnetwork plant: {\n  wires:\n    sensor filter_in\n}") {
+            Err(CompileError::Parse { line, column, message, .. }) => {
+                assert_eq!((line, column), (4, 12));
+                assert_eq!(message, "Expected `->` after wire source `sensor`");
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_coil_modifiers() {
+        let module = parse("module m\ncoil trip [latching, critical]\ncoil held [latching] unit: \"bar\"\ncoil plain").unwrap();
+        assert_eq!((module.coils[0].latching, module.coils[0].critical), (Some(true), Some(true)));
+        assert_eq!((module.coils[1].latching, module.coils[1].critical), (Some(true), None));
+        assert_eq!(module.coils[1].unit.as_deref(), Some("bar"));
+        assert_eq!((module.coils[2].latching, module.coils[2].critical), (None, None));
+        
+        // A parameter spelled like a modifier is just a parameter
+        let module = parse("module m\ncoil valve(latching)").unwrap();
+        assert_eq!(module.coils[0].parameters, ["latching"]);
+        assert_eq!(module.coils[0].latching, None);
+        
+        let err = parse("module m\ncoil trip [latching, sticky]").unwrap_err();
+        assert!(err.to_string().contains("Unknown coil modifier `sticky` (expected latching or critical)"), "{}", err);
+        let err = parse("module m\ncoil trip latching").unwrap_err();
+        assert_eq!(err.suggestion(), Some("[latching]"));
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let source = "module m\n/* disabled:\nrung old:\n  when NO a\n  then energise x\n*/\nsignal a /* inline */ coil x\nrung r: when NO a then energise 42";
+        match parse(source) {
+            Err(CompileError::Parse { line, message, .. }) => {
+                assert_eq!(line, 8);
+                assert_eq!(message, "Expected coil name");
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        let module = parse(&source.replace("energise 42", "energise x")).unwrap();
+        assert_eq!(module.rungs.len(), 1);
+        assert_eq!(module.coils.len(), 1);
+        
+        match parse("module m\nsignal a\n  /* never closed\nsignal b") {
+            Err(CompileError::Parse { line, column, message, .. }) => {
+                assert_eq!((line, column), (3, 3));
+                assert!(message.starts_with("Unterminated block comment"), "{}", message);
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert!(parse("module m\n/* outer /* inner */ */").is_err());
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let module = parse(r#"module m
+context: "line one\nline two\t\"quoted\" \\ \u{e9}\r""#).unwrap();
+        assert_eq!(module.context.as_deref(), Some("line one\nline two\t\"quoted\" \\ \u{e9}\r"));
+        
+        for (escape, shown) in [(r"\q", r"\q"), (r"\u{110000}", r"\u{110000}"), (r"\u{zz}", r"\u{zz}"), (r"\u41", r"\u")] {
+            match parse(&format!("module m\ncontext: \"bad {} here\"", escape)) {
+                Err(CompileError::Parse { line, column, message, .. }) => {
+                    assert_eq!((line, column), (2, 10));
+                    assert_eq!(message, format!("Unknown escape sequence `{}` in string", shown));
+                }
+                other => panic!("expected a parse error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_feature_gate() {
+        let module = parse("module m\n#[if feature(\"debug\")]\nsignal probe\nsignal a").unwrap();
+        assert_eq!(module.signals[0].feature.as_deref(), Some("debug"));
+        assert_eq!(module.signals[1].feature, None);
+        
+        match parse("module m\n#[if debug]\nsignal probe") {
+            Err(CompileError::Parse { line, column, message, .. }) => {
+                assert_eq!((line, column), (2, 6));
+                assert!(message.contains("if feature"), "{}", message);
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_error_suggestions() {
+        let error = parse("module m\nsignal end\n").unwrap_err();
+        assert!(error.to_string().contains("`end` is a reserved word"), "{}", error);
+        assert_eq!(error.suggestion(), Some("end_signal"));
+        
+        let error = parse("module m\nsignal a\ncoil x\nrung r when NO a then energise x\n").unwrap_err();
+        assert_eq!(error.suggestion(), Some(":"));
+    }
+
+    #[test]
+    fn test_missing_then_is_a_parse_error() {
+        match parse("module m\nrung r:\n  when NO a\n  energise x\n") {
+            Err(CompileError::Parse { line, column, message, .. }) => {
+                assert_eq!((line, column), (4, 3));
+                assert_eq!(message, "Expected `then` after the guard of rung r");
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        // `then` with nothing after it parses; the resolver warns about the empty rung
+        assert!(parse("module m\nrung r:\n  when NO a\n  then\n").unwrap().rungs[0].actions.is_empty());
+    }
+
+    #[test]
+    fn test_deep_guard_nesting_is_an_error() {
+        let depth = 10_000;
+        let guard = format!("{}NO a{}", "(".repeat(depth), ")".repeat(depth));
+        match parse(&format!("module m\nrung r:\n  when {}\n  then energise x", guard)) {
+            Err(CompileError::Parse { line, column, message, .. }) => {
+                assert_eq!(message, format!("guard nesting too deep (limit {})", DEFAULT_MAX_GUARD_DEPTH));
+                assert_eq!((line, column), (3, 8 + DEFAULT_MAX_GUARD_DEPTH));
+            }
+            other => panic!("expected a depth error, got {:?}", other),
+        }
+        
+        let mut parser = Parser::new("module m\nrung r:\n  when ((NO a))\n  then energise x").with_max_guard_depth(1);
+        assert!(parser.parse_module().is_err());
+    }
+
+    #[test]
+    fn test_long_flat_guard_is_an_error() {
+        let rung = |terms: usize| format!("module m\nrung r:\n  when {}\n  then energise x", vec!["NO a"; terms].join(" AND "));
+        match parse(&rung(10_000)) {
+            Err(CompileError::Parse { message, .. }) => {
+                assert_eq!(message, format!("guard nesting too deep (limit {})", DEFAULT_MAX_GUARD_DEPTH));
+            }
+            other => panic!("expected a depth error, got {:?}", other),
+        }
+        assert!(parse(&rung(DEFAULT_MAX_GUARD_DEPTH + 2)).is_err());
+        
+        // The deepest accepted guard, under NOT and in a require, still loads back from IR
+        let module = parse(&format!("module m\nrung r:\n  when NOT ({0})\n  then require {0}", vec!["NO a"; DEFAULT_MAX_GUARD_DEPTH].join(" OR "))).unwrap();
+        let ir = crate::emit_ir(&module).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&ir).is_ok());
+    }
+
+    #[test]
+    fn test_bad_argument_reports_its_line() {
+        let mut source = String::from("module m\nsignal a\ncoil x\n");
+        source.push_str("// padding\n".repeat(5).as_str());
+        source.push_str("rung r: when NO a then energise x(,)");
+        match parse(&source) {
+            Err(CompileError::Parse { line, column, message, .. }) => {
+                assert_eq!((line, column), (9, 35));
+                assert!(message.starts_with("Expected expression"), "{}", message);
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_oversized_number_literal_is_an_error() {
+        let source = format!(
+            "module m\ncoil c(level)\nrung r:\n  when NO a\n  then energise c({})\n",
+            "9".repeat(400)
+        );
+        match parse(&source) {
+            Err(CompileError::Parse { line, message, .. }) => {
+                assert_eq!(line, 5);
+                assert!(message.contains("Number literal out of range"), "{}", message);
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        
+        // Integers above 2^53 cannot be represented exactly
+        assert!(parse("module m\nrung r:\n  when a\n  then energise c(9007199254740993)").is_err());
+        assert!(parse("module m\nrung r:\n  when a\n  then energise c(9007199254740992)").is_ok());
+    }
+
+    #[test]
+    fn test_parse_set_and_reset() {
+        let module = parse("module m\nrung r:\n  when NO a\n  then set alarm, reset horn").unwrap();
+        let actions = &module.rungs[0].actions;
+        assert_eq!((actions[0].action_type, actions[0].coil.as_str()), (ActionType::Set, "alarm"));
+        assert_eq!((actions[1].action_type, actions[1].coil.as_str()), (ActionType::Reset, "horn"));
+        
+        match parse("module m\nrung reset:\n  when NO a\n  then set alarm") {
+            Err(CompileError::Parse { message, suggestion, .. }) => {
+                assert_eq!(message, "`reset` is a reserved word and cannot be used as a rung name");
+                assert_eq!(suggestion.as_deref(), Some("reset_rung"));
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_escalate() {
+        let module = parse("module m\ncoil alarm\nrung r:\n  when NO a\n  then escalate alarm(\"level2\")").unwrap();
+        let action = &module.rungs[0].actions[0];
+        assert_eq!(action.action_type, ActionType::Escalate);
+        assert_eq!(action.coil, "alarm");
+        assert!(matches!(&action.arguments[..], [Expr::String(level)] if level == "level2"));
+        assert!(action.value.is_none() && action.condition.is_none());
+    }
+
+    #[test]
+    fn test_parse_edge_contacts() {
+        let module = parse("module m\nrung r:\n  when P start AND NOT N stop\n  then energise run").unwrap();
+        assert_eq!(module.rungs[0].guard.to_string(), "P start AND NOT N stop");
+        match &module.rungs[0].guard {
+            GuardExpr::And { left, .. } => assert!(matches!(
+                left.as_ref(),
+                GuardExpr::Contact { contact_type: ContactType::RisingEdge, name, .. } if name == "start"
+            )),
+            other => panic!("expected AND, got {:?}", other),
+        }
+        
+        match parse("module m\nrung r:\n  when N\n  then energise run") {
+            Err(CompileError::Parse { line, column, message, .. }) => {
+                assert_eq!((line, column), (4, 3));
+                assert_eq!(message, "Expected signal/coil name after N");
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        match parse("module m\nsignal P\n") {
+            Err(CompileError::Parse { message, suggestion, .. }) => {
+                assert_eq!(message, "`P` is a reserved word and cannot be used as a signal name");
+                assert_eq!(suggestion.as_deref(), Some("P_signal"));
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_comparison_guards() {
+        let module = parse("module m\nrung r:\n  when temperature > 80 AND NOT level >= -5\n  then energise fan").unwrap();
+        assert_eq!(module.rungs[0].guard.to_string(), "temperature > 80 AND NOT level >= -5");
+        match &module.rungs[0].guard {
+            GuardExpr::And { left, .. } => assert!(matches!(
+                left.as_ref(),
+                GuardExpr::Compare { left: Expr::Identifier(name), op: CompareOp::Gt, right: Expr::Number(n) } if name == "temperature" && *n == 80.0
+            )),
+            other => panic!("expected AND, got {:?}", other),
+        }
+        
+        match parse("module m\nrung r:\n  when level >\n  then energise fan") {
+            Err(CompileError::Parse { line, .. }) => assert_eq!(line, 4),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_minus_in_action_arguments() {
+        let module = parse("module m\nrung r:\n  when NO a\n  then energise x(-3)").unwrap();
+        assert!(matches!(module.rungs[0].actions[0].arguments[..], [Expr::Number(n)] if n == -3.0));
+        
+        for source in ["module m\nrung r:\n  when NO a\n  then energise x(-)", "module m\nrung r:\n  when NO a\n  then energise x(- -3)"] {
+            match parse(source) {
+                Err(CompileError::Parse { line, column, message, .. }) => {
+                    assert_eq!((line, column), (4, 19));
+                    assert_eq!(message, "Expected a number after '-'");
+                }
+                other => panic!("expected a parse error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_end_terminated_rungs() {
+        let source = r#"
+module m
+rung with_end:
+  when NO a
+  then energise b
+  end
+rung with_else:
+  when NO a
+  then energise b
+  else de_energise b
+  end
+rung without_end:
+  when NO a
+  then energise c
+"#;
+        let module = parse(source).unwrap();
+        assert_eq!(module.rungs.len(), 3);
+        assert_eq!(module.rungs[0].actions.len(), 1);
+        assert!(module.rungs[0].else_actions.is_empty());
+        assert_eq!(module.rungs[1].else_actions[0].action_type, ActionType::DeEnergise);
+        
+        match parse("module m\nrung r:\n  when NO a\n  then\n  end\n") {
+            Err(CompileError::Parse { line, message, .. }) => {
+                assert_eq!(line, 5);
+                assert_eq!(message, "Expected an action after `then` in rung r");
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_multiline_commented_parameter_lists() {
+        let source = r#"
+module m
+
+signal reading(
+  zone,   // first
+  sensor  // second
+)
+
+coil alarm(
+  // the zone raising the alarm
+  zone,
+)
+
+rung r:
+  when NO reading(
+    "north", // zone
+    1
+  )
+  then energise alarm("north")
+"#;
+        let module = parse(source).unwrap();
+        assert_eq!(module.signals[0].parameters, vec!["zone", "sensor"]);
+        assert_eq!(module.coils[0].parameters, vec!["zone"]);
+        match &module.rungs[0].guard {
+            GuardExpr::Contact { arguments, .. } => assert_eq!(arguments.len(), 2),
+            other => panic!("expected a contact, got {:?}", other),
+        }
+        
+        match parse("module m\nsignal s(\n  a,\n  1\n)") {
+            Err(CompileError::Parse { line, message, .. }) => {
+                assert_eq!(line, 4);
+                assert!(message.contains("Expected parameter name"));
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    fn parse_guard_source(guard: &str) -> (GuardExpr, Vec<Diagnostic>) {
+        let source = format!("module m\nrung r:\n  when {}\n  then energise c", guard);
+        let (module, warnings) = parse_with_warnings(&source).unwrap();
+        (module.rungs[0].guard.clone(), warnings)
+    }
+
+    #[test]
+    fn test_not_binds_to_next_primary() {
+        let (guard, warnings) = parse_guard_source("NOT a AND b");
+        match guard {
+            GuardExpr::And { left, right } => {
+                assert!(matches!(*left, GuardExpr::Not { .. }));
+                assert!(matches!(*right, GuardExpr::Contact { .. }));
+            }
+            other => panic!("expected AND at the root, got {:?}", other),
+        }
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("parentheses"));
+        
+        let (guard, warnings) = parse_guard_source("NOT NO a OR b");
+        assert!(matches!(guard, GuardExpr::Or { .. }));
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_parenthesized_not_does_not_warn() {
+        let (guard, warnings) = parse_guard_source("NOT (a AND b)");
+        assert!(matches!(guard, GuardExpr::Not { .. }));
+        assert!(warnings.is_empty());
+        
+        let (_, warnings) = parse_guard_source("(NOT a) AND b");
+        assert!(warnings.is_empty());
+        
+        let (_, warnings) = parse_guard_source("b AND NOT a");
+        assert!(warnings.is_empty());
+    }
 }
@@ -0,0 +1,147 @@
+use crate::ast::ActionType;
+use crate::parser::{lex, Token};
+use std::fmt;
+
+/// What the source expects at a cursor position, for editor completion
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartialContext {
+    /// Between top-level declarations
+    TopLevel,
+    /// After `module`
+    ModuleName,
+    /// After a declaration keyword such as `signal` or `rung`
+    DeclarationName { kind: &'static str },
+    /// Inside the parameter list of a signal or coil
+    Parameters { declaration: String },
+    /// Inside a rung, before `when`
+    RungHeader { rung: String },
+    /// Inside a rung guard, between `when` and `then`
+    RungGuard { rung: String },
+    /// After `NO`/`NC`, expecting a signal or coil name
+    ContactName,
+    /// After `then`, expecting actions
+    RungActions { rung: String },
+    /// After an action keyword, expecting the coil it targets
+    CoilName { action: ActionType },
+}
+
+impl fmt::Display for PartialContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartialContext::TopLevel => write!(f, "top level: declaration expected"),
+            PartialContext::ModuleName => write!(f, "module name expected"),
+            PartialContext::DeclarationName { kind } => write!(f, "{} name expected", kind),
+            PartialContext::Parameters { declaration } => write!(f, "inside parameters of {}", declaration),
+            PartialContext::RungHeader { rung } => write!(f, "inside rung {}: `when` expected", rung),
+            PartialContext::RungGuard { rung } => write!(f, "inside rung guard of {}", rung),
+            PartialContext::ContactName => write!(f, "signal or coil name expected after contact"),
+            PartialContext::RungActions { rung } => write!(f, "inside rung actions of {}", rung),
+            PartialContext::CoilName { action } => write!(f, "coil name expected after {:?}", action),
+        }
+    }
+}
+
+/// Determine the construct enclosing byte `offset` by walking the tokens before it
+pub fn parse_partial_at(source: &str, offset: usize) -> PartialContext {
+    let mut tokens: Vec<(Token, usize)> = lex(source)
+        .into_iter()
+        .take_while(|(_, span)| span.end <= offset)
+        .map(|(token, span)| (token, span.end))
+        .collect();
+    
+    // A word touching the cursor is the prefix being completed
+    if let Some((Token::Identifier(_), end)) = tokens.last() {
+        if *end == offset {
+            tokens.pop();
+        }
+    }
+    let mut tokens: Vec<Token> = tokens.into_iter().map(|(token, _)| token).collect();
+    
+    let Some(last) = tokens.last().cloned() else {
+        return PartialContext::TopLevel;
+    };
+    
+    match last {
+        Token::Module => return PartialContext::ModuleName,
+        Token::Signal => return PartialContext::DeclarationName { kind: "signal" },
+        Token::Coil => return PartialContext::DeclarationName { kind: "coil" },
+        Token::Rung => return PartialContext::DeclarationName { kind: "rung" },
+        Token::Block => return PartialContext::DeclarationName { kind: "block" },
+        Token::Network => return PartialContext::DeclarationName { kind: "network" },
+        Token::NO | Token::NC | Token::P | Token::N => return PartialContext::ContactName,
+        Token::Energise => return PartialContext::CoilName { action: ActionType::Energise },
+        Token::DeEnergise => return PartialContext::CoilName { action: ActionType::DeEnergise },
+        Token::Escalate => return PartialContext::CoilName { action: ActionType::Escalate },
+        Token::Require => return PartialContext::CoilName { action: ActionType::Require },
+        Token::Set => return PartialContext::CoilName { action: ActionType::Set },
+        Token::Reset => return PartialContext::CoilName { action: ActionType::Reset },
+        _ => {}
+    }
+    
+    // Find the innermost top-level declaration the cursor is in
+    let Some(start) = tokens.iter().rposition(|t| matches!(
+        t,
+        Token::Signal | Token::Coil | Token::Rung | Token::Block | Token::Network | Token::Module
+    )) else {
+        return PartialContext::TopLevel;
+    };
+    let declaration = tokens.split_off(start);
+    let name = match declaration.get(1) {
+        Some(Token::Identifier(name)) => name.clone(),
+        _ => String::new(),
+    };
+    
+    match declaration[0] {
+        Token::Rung => {
+            if declaration.contains(&Token::Then) {
+                PartialContext::RungActions { rung: name }
+            } else if declaration.contains(&Token::When) {
+                PartialContext::RungGuard { rung: name }
+            } else {
+                PartialContext::RungHeader { rung: name }
+            }
+        }
+        Token::Signal | Token::Coil => {
+            let open = declaration.iter().filter(|t| **t == Token::LParen).count();
+            let closed = declaration.iter().filter(|t| **t == Token::RParen).count();
+            if open > closed {
+                PartialContext::Parameters { declaration: name }
+            } else {
+                PartialContext::TopLevel
+            }
+        }
+        _ => PartialContext::TopLevel,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_context_after_energise() {
+        let source = "module m\nsignal a\ncoil b\nrung r:\n  when NO a\n  then energise ";
+        assert_eq!(
+            parse_partial_at(source, source.len()),
+            PartialContext::CoilName { action: ActionType::Energise }
+        );
+        assert_eq!(
+            parse_partial_at(source, source.len()).to_string(),
+            "coil name expected after Energise"
+        );
+        
+        // A partially typed coil name is still completed as a coil name
+        let typing = format!("{}ou", source);
+        assert_eq!(
+            parse_partial_at(&typing, typing.len()),
+            PartialContext::CoilName { action: ActionType::Energise }
+        );
+        
+        let guard_offset = source.find("NO a").unwrap() + "NO a".len();
+        assert_eq!(
+            parse_partial_at(source, guard_offset + 1),
+            PartialContext::RungGuard { rung: "r".to_string() }
+        );
+    }
+}
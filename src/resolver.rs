@@ -1,6 +1,29 @@
 use crate::ast;
-use crate::error::{CompileError, Result};
-use std::collections::HashMap;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::error::{at, CompileError, Result};
+use crate::eval::{is_contradiction, is_tautology};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// What a declared name refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Signal,
+    Coil,
+    Block,
+}
+
+/// Everything tooling needs to describe a declared name
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolInfo {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Declared type of a signal, or effect of a block
+    pub type_: Option<String>,
+    pub parameters: Vec<String>,
+    pub latching: bool,
+    pub critical: bool,
+    pub unit: Option<String>,
+}
 
 /// Symbol table for name resolution
 pub struct SymbolTable {
@@ -18,9 +41,34 @@ impl SymbolTable {
         }
     }
     
+    /// Build a table from every declaration in `module`
+    pub fn from_module(module: &ast::Module) -> Result<Self> {
+        let mut errors = Vec::new();
+        let symbols = Self::collect(module, &mut errors);
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(symbols),
+        }
+    }
+    
+    /// Build a table, recording duplicate declarations in `errors` and keeping the first of each
+    fn collect(module: &ast::Module, errors: &mut Vec<CompileError>) -> Self {
+        let mut symbols = Self::new();
+        for signal in &module.signals {
+            record(symbols.add_signal(signal.clone()), errors);
+        }
+        for coil in &module.coils {
+            record(symbols.add_coil(coil.clone()), errors);
+        }
+        for block in &module.blocks {
+            symbols.blocks.insert(block.name.clone(), block.clone());
+        }
+        symbols
+    }
+    
     pub fn add_signal(&mut self, signal: ast::SignalDecl) -> Result<()> {
         if self.signals.contains_key(&signal.name) {
-            return Err(CompileError::NameResolution(
+            return Err(CompileError::name_resolution(
                 format!("Duplicate signal name: {}", signal.name)
             ));
         }
@@ -30,7 +78,7 @@ impl SymbolTable {
     
     pub fn add_coil(&mut self, coil: ast::CoilDecl) -> Result<()> {
         if self.coils.contains_key(&coil.name) {
-            return Err(CompileError::NameResolution(
+            return Err(CompileError::name_resolution(
                 format!("Duplicate coil name: {}", coil.name)
             ));
         }
@@ -46,67 +94,779 @@ impl SymbolTable {
         self.coils.get(name)
     }
     
+    /// Look up a name across signals, coils and blocks
+    pub fn symbol_info(&self, name: &str) -> Option<SymbolInfo> {
+        if let Some(signal) = self.signals.get(name) {
+            return Some(SymbolInfo {
+                name: signal.name.clone(),
+                kind: SymbolKind::Signal,
+                type_: signal.type_.clone(),
+                parameters: signal.parameters.clone(),
+                latching: false,
+                critical: false,
+                unit: signal.unit.clone(),
+            });
+        }
+        if let Some(coil) = self.coils.get(name) {
+            return Some(SymbolInfo {
+                name: coil.name.clone(),
+                kind: SymbolKind::Coil,
+                type_: None,
+                parameters: coil.parameters.clone(),
+                latching: coil.latching.unwrap_or(false),
+                critical: coil.critical.unwrap_or(false),
+                unit: coil.unit.clone(),
+            });
+        }
+        self.blocks.get(name).map(|block| SymbolInfo {
+            name: block.name.clone(),
+            kind: SymbolKind::Block,
+            type_: block.effect.clone(),
+            parameters: Vec::new(),
+            latching: false,
+            critical: false,
+            unit: None,
+        })
+    }
+    
+    /// Warn when two symbols compared against each other declare different units
+    pub fn unit_mismatch(&self, left: &str, right: &str) -> Option<Diagnostic> {
+        let left = self.symbol_info(left)?;
+        let right = self.symbol_info(right)?;
+        match (&left.unit, &right.unit) {
+            (Some(left_unit), Some(right_unit)) if left_unit != right_unit => Some(Diagnostic::warning(format!(
+                "comparing '{}' ({}) with '{}' ({}) mixes units",
+                left.name, left_unit, right.name, right_unit
+            ))),
+            _ => None,
+        }
+    }
+    
     pub fn resolve_signal(&self, name: &str) -> Result<()> {
         if !self.signals.contains_key(name) {
-            return Err(CompileError::NameResolution(
-                format!("Undefined signal: {}", name)
-            ));
+            return Err(CompileError::NameResolution {
+                message: format!("Undefined signal: {}", name),
+                suggestion: closest_name(name, self.signals.keys()),
+            });
         }
         Ok(())
     }
     
+    /// Resolve a contact name, which may read either a signal or a coil
+    pub fn resolve_contact(&self, name: &str) -> Result<ast::ContactKind> {
+        if self.signals.contains_key(name) {
+            Ok(ast::ContactKind::Signal)
+        } else if self.coils.contains_key(name) {
+            Ok(ast::ContactKind::Coil)
+        } else {
+            Err(CompileError::NameResolution {
+                message: format!("Undefined signal: {}", name),
+                suggestion: closest_name(name, self.signals.keys()),
+            })
+        }
+    }
+    
+    /// Check a contact passes no more positional arguments than its signal or coil declares
+    pub fn check_contact_arguments(&self, name: &str, found: usize) -> Result<()> {
+        let (kind, parameters) = match (self.signals.get(name), self.coils.get(name)) {
+            (Some(signal), _) => ("signal", &signal.parameters),
+            (None, Some(coil)) => ("coil", &coil.parameters),
+            (None, None) => return Ok(()),
+        };
+        if found <= parameters.len() {
+            return Ok(());
+        }
+        let declared = if parameters.is_empty() {
+            "no parameters".to_string()
+        } else {
+            format!("parameters ({})", parameters.join(", "))
+        };
+        Err(CompileError::name_resolution(format!(
+            "Contact '{}' passes {} argument(s), but {} '{}' declares {}",
+            name, found, kind, name, declared
+        )))
+    }
+    
+    /// Check `set` and `reset` only target latching coils, the only ones that hold state
+    pub fn check_latch_target(&self, action: &ast::Action) -> Result<()> {
+        if !matches!(action.action_type, ast::ActionType::Set | ast::ActionType::Reset) {
+            return Ok(());
+        }
+        match self.coils.get(&action.coil) {
+            Some(coil) if coil.latching != Some(true) => Err(CompileError::Type(format!(
+                "`{}` targets coil '{}', which is not latching (declare it as `coil {} [latching]`)",
+                action.action_type.ir_name(), coil.name, coil.name
+            ))),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Check an action passes exactly one argument per coil parameter
+    pub fn check_arity(&self, action: &ast::Action) -> Result<()> {
+        let Some(coil) = self.coils.get(&action.coil) else {
+            return Ok(());
+        };
+        let (expected, found) = (coil.parameters.len(), action.arguments.len());
+        let problem = match found.cmp(&expected) {
+            std::cmp::Ordering::Equal => return Ok(()),
+            std::cmp::Ordering::Greater => "Too many",
+            std::cmp::Ordering::Less => "Too few",
+        };
+        Err(CompileError::Type(format!(
+            "{} arguments to coil '{}': expected {}, found {}",
+            problem, coil.name, expected, found
+        )))
+    }
+    
+    /// Resolve the precondition named by `require name`, a signal or a coil
+    pub fn resolve_require_target(&self, name: &str) -> Result<()> {
+        if self.signals.contains_key(name) || self.coils.contains_key(name) {
+            return Ok(());
+        }
+        Err(CompileError::NameResolution {
+            message: format!("Undefined signal or coil in require: {}", name),
+            suggestion: closest_name(name, self.signals.keys().chain(self.coils.keys())),
+        })
+    }
+    
     pub fn resolve_coil(&self, name: &str) -> Result<()> {
         if !self.coils.contains_key(name) {
-            return Err(CompileError::NameResolution(
-                format!("Undefined coil: {}", name)
-            ));
+            return Err(CompileError::NameResolution {
+                message: format!("Undefined coil: {}", name),
+                suggestion: closest_name(name, self.coils.keys()),
+            });
         }
         Ok(())
     }
 }
 
-/// Resolve all names in a module
-pub fn resolve_names(module: &mut ast::Module) -> Result<()> {
-    let mut symbols = SymbolTable::new();
+/// The candidate nearest to `name` by edit distance, if it is close enough
+/// to be a likely typo; ties go to the alphabetically first candidate
+pub fn closest_name<'a>(name: &str, candidates: impl Iterator<Item = &'a String>) -> Option<String> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (strsim::levenshtein(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min()
+        .map(|(_, candidate)| candidate.clone())
+}
+
+/// Options controlling how strictly resolution enforces policy checks
+#[derive(Debug, Clone, Default)]
+pub struct ResolveOptions {
+    /// Report every lint as an error rather than a warning
+    pub strict: bool,
+    /// Warn about signals read as NO in some rungs and NC in others
+    pub mixed_contacts: bool,
+    /// Enabled features; declarations gated on any other feature are dropped
+    pub features: Vec<String>,
+    /// Coils exempt from the note on NC contacts that can never pass
+    pub allow_nc: Vec<String>,
+}
+
+/// Resolve all names in a module, returning any warnings
+pub fn resolve_names(module: &mut ast::Module) -> Result<Vec<Diagnostic>> {
+    resolve_names_with(module, &ResolveOptions::default())
+}
+
+/// Resolve all names in a module with explicit options
+pub fn resolve_names_with(module: &mut ast::Module, options: &ResolveOptions) -> Result<Vec<Diagnostic>> {
+    let mut errors = Vec::new();
+    let warnings = resolve_collecting(module, options, &mut errors);
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error);
+    }
+    
+    if options.strict && warnings.iter().any(|w| w.severity == Severity::Warning) {
+        let messages: Vec<&str> = warnings.iter()
+            .filter(|w| w.severity == Severity::Warning)
+            .map(|w| w.message.as_str())
+            .collect();
+        return Err(CompileError::Type(messages.join("; ")));
+    }
+    
+    Ok(warnings)
+}
+
+/// Resolve a possibly incomplete module without aborting.
+///
+/// Every error, such as each undefined reference, is reported as an error
+/// diagnostic, followed by the usual warnings; names that do resolve are
+/// still filled in.
+pub fn resolve_lenient(module: &mut ast::Module) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    let warnings = resolve_collecting(module, &ResolveOptions::default(), &mut errors);
+    errors.iter().map(Diagnostic::from).chain(warnings).collect()
+}
+
+/// Drop declarations gated on a feature that is not in `features`
+pub fn apply_features(module: &mut ast::Module, features: &[String]) {
+    let enabled = |feature: &Option<String>| feature.as_ref().is_none_or(|f| features.contains(f));
+    module.signals.retain(|s| enabled(&s.feature));
+    module.coils.retain(|c| enabled(&c.feature));
+    module.rungs.retain(|r| enabled(&r.feature));
+    module.blocks.retain(|b| enabled(&b.feature));
+    module.networks.retain(|n| enabled(&n.feature));
+}
+
+fn record<T>(result: Result<T>, errors: &mut Vec<CompileError>) {
+    if let Err(error) = result {
+        errors.push(error);
+    }
+}
+
+/// Run every resolution check, recording errors in order and returning warnings
+fn resolve_collecting(module: &mut ast::Module, options: &ResolveOptions, errors: &mut Vec<CompileError>) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    apply_features(module, &options.features);
     
     // First pass: collect all declarations
-    for signal in &module.signals {
-        symbols.add_signal(signal.clone())?;
+    let symbols = SymbolTable::collect(module, errors);
+    check_unique_names(module, errors);
+    
+    // Second pass: resolve references in rungs
+    for rung in &mut module.rungs {
+        resolve_guard(&mut rung.guard, &symbols, errors);
+        for action in rung.actions.iter_mut().chain(rung.else_actions.iter_mut()) {
+            if let Some(condition) = &mut action.condition {
+                resolve_guard(condition, &symbols, errors);
+                continue;
+            }
+            // A require names a precondition, which may be a signal as well as a coil
+            if action.action_type == ast::ActionType::Require {
+                record(symbols.resolve_require_target(&action.coil), errors);
+                continue;
+            }
+            record(symbols.resolve_coil(&action.coil), errors);
+            if action.action_type == ast::ActionType::Energise {
+                record(symbols.check_arity(action), errors);
+            }
+            record(symbols.check_latch_target(action), errors);
+        }
+    }
+    
+    for rung in &module.rungs {
+        record(check_block_effects(rung, &module.blocks), errors);
+    }
+    
+    for block in &module.blocks {
+        record(block_composition(&module.blocks, block), errors);
+    }
+    
+    // Resolve network wiring
+    for network in &module.networks {
+        record(check_wire_targets(network), errors);
     }
     
+    // A critical latch with no way to clear it stays stuck on
     for coil in &module.coils {
-        symbols.add_coil(coil.clone())?;
+        if coil.critical == Some(true) && coil.latching == Some(true) {
+            let has_reset = module.rungs.iter().any(|rung| rung.actions.iter().chain(&rung.else_actions).any(|action| {
+                action.coil == coil.name && matches!(action.action_type, ast::ActionType::DeEnergise | ast::ActionType::Reset)
+            }));
+            if !has_reset {
+                warnings.push(Diagnostic::warning(format!(
+                    "critical coil '{}' is latching but no rung de-energises it", coil.name
+                )));
+            }
+        }
     }
     
-    // Second pass: resolve references in rungs
+    // A block with no ports and no body is almost always an unfinished stub
+    for block in &module.blocks {
+        let is_empty = block.inputs.is_empty() && block.outputs.is_empty() && block.implementation.is_none();
+        if is_empty && !block.is_abstract {
+            warnings.push(Diagnostic::warning(format!(
+                "block '{}' is empty (declare it as `abstract block` if intended)", block.name
+            )));
+        }
+    }
+    
+    // `then` followed by no action parses, but the rung does nothing
+    for rung in &module.rungs {
+        if rung.actions.is_empty() {
+            warnings.push(Diagnostic::warning(format!(
+                "rung '{}' has no actions after `then`", rung.name
+            )));
+        }
+    }
+    
+    // Comparing signals with different units is almost always a conversion bug
     for rung in &module.rungs {
-        resolve_guard(&rung.guard, &symbols)?;
-        for action in &rung.actions {
-            symbols.resolve_coil(&action.coil)?;
+        let mut pairs = Vec::new();
+        collect_signal_comparisons(&rung.guard, &mut pairs);
+        for condition in rung.actions.iter().chain(&rung.else_actions).filter_map(|a| a.condition.as_ref()) {
+            collect_signal_comparisons(condition, &mut pairs);
         }
+        warnings.extend(pairs.into_iter().filter_map(|(left, right)| symbols.unit_mismatch(left, right)));
     }
     
+    warnings.extend(deprecation_warnings(module));
+    warnings.extend(redundant_driver_warnings(module));
+    warnings.extend(conflicting_driver_warnings(module, errors));
+    // Unresolved references would make their intended targets look unused
+    if errors.is_empty() {
+        warnings.extend(unused_warnings(module));
+    }
+    
+    // A require that can never hold fails the rung every scan
+    for rung in &module.rungs {
+        for action in rung.actions.iter().chain(&rung.else_actions) {
+            if let Some(condition) = action.condition.as_ref().filter(|c| is_contradiction(c)) {
+                warnings.push(Diagnostic::warning(format!(
+                    "rung '{}' can never complete its actions: `require {}` can never hold",
+                    rung.name, condition
+                )));
+            }
+        }
+    }
+    
+    if options.mixed_contacts {
+        warnings.extend(mixed_contact_warnings(module));
+    }
+    warnings.extend(stuck_nc_notes(module, &options.allow_nc));
+    
+    // Libraries only provide declarations, so they have no entry point to lint
+    if !module.is_library && module.rungs.is_empty() {
+        warnings.push(Diagnostic::warning(
+            format!("module '{}' has no rungs (declare it as `library module` if intended)", module.name)
+        ));
+    }
+    
+    warnings
+}
+
+/// Map each coil to the signals read by the guards of rungs that drive it.
+///
+/// Every declared coil has an entry; signal lists are sorted and de-duplicated.
+pub fn coil_dependencies(module: &ast::Module) -> BTreeMap<String, Vec<String>> {
+    let signals: BTreeSet<&str> = module.signals.iter().map(|s| s.name.as_str()).collect();
+    let mut dependencies: BTreeMap<String, BTreeSet<String>> = module.coils.iter()
+        .map(|c| (c.name.clone(), BTreeSet::new()))
+        .collect();
+    
+    for rung in &module.rungs {
+        let mut names = Vec::new();
+        collect_contact_names(&rung.guard, &mut names);
+        for action in rung.actions.iter().chain(&rung.else_actions).filter(|a| a.action_type != ast::ActionType::Require) {
+            if let Some(deps) = dependencies.get_mut(&action.coil) {
+                deps.extend(names.iter().filter(|n| signals.contains(*n)).map(|n| n.to_string()));
+            }
+        }
+    }
+    
+    dependencies.into_iter()
+        .map(|(coil, deps)| (coil, deps.into_iter().collect()))
+        .collect()
+}
+
+/// Reject side-effecting actions in rungs that reference a block declared
+/// `effect: "pure"`, either by reading or driving one of its outputs
+fn check_block_effects(rung: &ast::RungDecl, blocks: &[ast::BlockDecl]) -> Result<()> {
+    let actions: Vec<&ast::Action> = rung.actions.iter().chain(&rung.else_actions).collect();
+    let Some(effectful) = actions.iter().find(|a| a.action_type.has_side_effects()) else {
+        return Ok(());
+    };
+    
+    let mut names = Vec::new();
+    collect_contact_names(&rung.guard, &mut names);
+    names.extend(actions.iter().map(|a| a.coil.as_str()));
+    
+    for block in blocks.iter().filter(|b| b.effect.as_deref() == Some("pure")) {
+        if block.outputs.iter().any(|port| names.contains(&port.name.as_str())) {
+            return Err(CompileError::Type(format!(
+                "rung '{}' cannot {} because it references pure block '{}'",
+                rung.name, effectful.action_type.ir_name(), block.name
+            )));
+        }
+    }
     Ok(())
 }
 
-fn resolve_guard(guard: &ast::GuardExpr, symbols: &SymbolTable) -> Result<()> {
+/// Follow a block's `implementation` through other declared blocks.
+///
+/// Returns the chain starting at `block`; an implementation that names no
+/// block (such as an external callable) ends the chain.
+pub fn block_composition(blocks: &[ast::BlockDecl], block: &ast::BlockDecl) -> Result<Vec<String>> {
+    let mut chain = vec![block.name.clone()];
+    let mut current = block;
+    while let Some(next) = current.implementation.as_deref()
+        .and_then(|implementation| blocks.iter().find(|b| b.name == implementation))
+    {
+        if chain.contains(&next.name) {
+            chain.push(next.name.clone());
+            return Err(CompileError::name_resolution(
+                format!("Cyclic block composition: {}", chain.join(" -> "))
+            ));
+        }
+        chain.push(next.name.clone());
+        current = next;
+    }
+    Ok(chain)
+}
+
+/// Rungs, blocks and networks each need distinct names, or the VM cannot tell them apart
+fn check_unique_names(module: &ast::Module, errors: &mut Vec<CompileError>) {
+    let kinds = [
+        ("rung", module.rungs.iter().map(|r| r.name.as_str()).collect::<Vec<_>>()),
+        ("block", module.blocks.iter().map(|b| b.name.as_str()).collect()),
+        ("network", module.networks.iter().map(|n| n.name.as_str()).collect()),
+    ];
+    for (kind, names) in kinds {
+        let mut seen = HashSet::new();
+        for name in names {
+            if !seen.insert(name) {
+                errors.push(CompileError::name_resolution(format!("Duplicate {} name: {}", kind, name)));
+            }
+        }
+    }
+}
+
+/// Each wire target may have only one driver; two sources on one target short together
+fn check_wire_targets(network: &ast::NetworkDecl) -> Result<()> {
+    let mut drivers: HashMap<&str, &str> = HashMap::new();
+    for wire in &network.wires {
+        if let Some(first) = drivers.insert(&wire.target, &wire.source) {
+            return Err(CompileError::name_resolution(format!(
+                "Wire target '{}' in network '{}' is driven by both '{}' and '{}'{}",
+                wire.target, network.name, first, wire.source, at(&wire.position)
+            )));
+        }
+    }
+    
+    let mut outputs = HashSet::new();
+    for output in &network.outputs {
+        if !outputs.insert(&output.name) {
+            return Err(CompileError::name_resolution(format!(
+                "Output '{}' in network '{}' is declared more than once{}",
+                output.name, network.name, at(&output.position)
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Signals read through both NO and NC contacts, naming the rungs for each
+fn mixed_contact_warnings(module: &ast::Module) -> Vec<Diagnostic> {
+    let mut reads: BTreeMap<&str, (BTreeSet<&str>, BTreeSet<&str>)> = BTreeMap::new();
+    for rung in &module.rungs {
+        let mut contacts = Vec::new();
+        collect_contacts_of_kind(&rung.guard, ast::ContactKind::Signal, &mut contacts);
+        for (name, contact_type) in contacts {
+            let (no_rungs, nc_rungs) = reads.entry(name).or_default();
+            match contact_type {
+                ast::ContactType::NO => no_rungs.insert(rung.name.as_str()),
+                ast::ContactType::NC => nc_rungs.insert(rung.name.as_str()),
+                ast::ContactType::RisingEdge | ast::ContactType::FallingEdge => false,
+            };
+        }
+    }
+    
+    reads.into_iter()
+        .filter(|(_, (no_rungs, nc_rungs))| !no_rungs.is_empty() && !nc_rungs.is_empty())
+        .map(|(name, (no_rungs, nc_rungs))| Diagnostic::warning(format!(
+            "signal '{}' is read as NO in {} but as NC in {}",
+            name,
+            no_rungs.into_iter().collect::<Vec<_>>().join(", "),
+            nc_rungs.into_iter().collect::<Vec<_>>().join(", ")
+        )))
+        .collect()
+}
+
+/// Resolved signal contacts with their contact types, in source order
+fn collect_contacts_of_kind<'a>(guard: &'a ast::GuardExpr, wanted: ast::ContactKind, contacts: &mut Vec<(&'a str, ast::ContactType)>) {
+    match guard {
+        ast::GuardExpr::Contact { name, contact_type, kind: Some(kind), .. } if *kind == wanted => {
+            contacts.push((name, *contact_type));
+        }
+        ast::GuardExpr::Contact { .. } | ast::GuardExpr::Compare { .. } => {}
+        ast::GuardExpr::And { left, right } | ast::GuardExpr::Or { left, right } => {
+            collect_contacts_of_kind(left, wanted, contacts);
+            collect_contacts_of_kind(right, wanted, contacts);
+        }
+        ast::GuardExpr::Not { expr } => collect_contacts_of_kind(expr, wanted, contacts),
+    }
+}
+
+/// Warnings for signals and coils no rung or network refers to.
+///
+/// Libraries exist to provide declarations, and a module without rungs is
+/// already warned about, so neither is checked.
+fn unused_warnings(module: &ast::Module) -> Vec<Diagnostic> {
+    if module.is_library || module.rungs.is_empty() {
+        return Vec::new();
+    }
+    
+    let mut used: HashSet<&str> = HashSet::new();
+    for rung in &module.rungs {
+        let mut names = Vec::new();
+        collect_contact_names(&rung.guard, &mut names);
+        for action in rung.actions.iter().chain(&rung.else_actions) {
+            if let Some(condition) = &action.condition {
+                collect_contact_names(condition, &mut names);
+            }
+            names.push(&action.coil);
+            for expr in action.arguments.iter().chain(&action.value) {
+                if let ast::Expr::Identifier(name) = expr {
+                    names.push(name);
+                }
+            }
+        }
+        used.extend(names);
+    }
+    for network in &module.networks {
+        used.extend(network.wires.iter().flat_map(|w| [w.source.as_str(), w.target.as_str()]));
+        used.extend(network.outputs.iter().map(|o| o.source.as_str()));
+    }
+    
+    let signals = module.signals.iter().map(|s| ("signal", &s.name));
+    let coils = module.coils.iter().map(|c| ("coil", &c.name));
+    signals.chain(coils)
+        .filter(|(_, name)| !used.contains(name.as_str()))
+        .map(|(kind, name)| Diagnostic::warning(format!("unused {} '{}'", kind, name)))
+        .collect()
+}
+
+/// Warnings for rungs that read or drive a declaration marked `@deprecated`.
+///
+/// Blocks are referenced through their output ports; each declaration is
+/// reported once per rung.
+fn deprecation_warnings(module: &ast::Module) -> Vec<Diagnostic> {
+    let mut deprecated: Vec<(&str, &str, &str, &str)> = Vec::new();
+    for signal in &module.signals {
+        if let Some(message) = &signal.deprecated {
+            deprecated.push((&signal.name, "signal", &signal.name, message));
+        }
+    }
+    for coil in &module.coils {
+        if let Some(message) = &coil.deprecated {
+            deprecated.push((&coil.name, "coil", &coil.name, message));
+        }
+    }
+    for block in &module.blocks {
+        if let Some(message) = &block.deprecated {
+            for port in &block.outputs {
+                deprecated.push((&port.name, "block", &block.name, message));
+            }
+        }
+    }
+    if deprecated.is_empty() {
+        return Vec::new();
+    }
+    
+    let mut warnings = Vec::new();
+    for rung in &module.rungs {
+        let mut names = Vec::new();
+        collect_contact_names(&rung.guard, &mut names);
+        for action in rung.actions.iter().chain(&rung.else_actions) {
+            match &action.condition {
+                Some(condition) => collect_contact_names(condition, &mut names),
+                None => names.push(&action.coil),
+            }
+        }
+        
+        let mut reported = HashSet::new();
+        for name in names {
+            for &(_, what, decl, message) in deprecated.iter().filter(|d| d.0 == name) {
+                if !reported.insert((what, decl)) {
+                    continue;
+                }
+                let reason = if message.is_empty() { String::new() } else { format!(": {}", message) };
+                warnings.push(Diagnostic::warning(format!(
+                    "rung '{}' uses deprecated {} '{}'{}", rung.name, what, decl, reason
+                )));
+            }
+        }
+    }
+    warnings
+}
+
+/// Conditional rungs energising a coil that another rung energises under a
+/// guard that always holds; their "on" direction never has an effect
+fn redundant_driver_warnings(module: &ast::Module) -> Vec<Diagnostic> {
+    let energises = |rung: &ast::RungDecl, coil: &str| rung.actions.iter().any(|action| {
+        action.coil == coil
+            && action.action_type == ast::ActionType::Energise
+            && !matches!(action.value, Some(ast::Expr::Boolean(false)))
+    });
+    
+    let mut warnings = Vec::new();
+    for coil in &module.coils {
+        let drivers: Vec<&ast::RungDecl> = module.rungs.iter().filter(|rung| energises(rung, &coil.name)).collect();
+        let (unconditional, conditional): (Vec<_>, Vec<_>) = drivers.into_iter().partition(|rung| is_tautology(&rung.guard));
+        if let (Some(always), false) = (unconditional.first(), conditional.is_empty()) {
+            let redundant: Vec<&str> = conditional.iter().map(|rung| rung.name.as_str()).collect();
+            warnings.push(Diagnostic::warning(format!(
+                "coil '{}' is energised unconditionally by rung {}, so energising it in {} has no effect",
+                coil.name, always.name, redundant.join(", ")
+            )));
+        }
+    }
+    warnings
+}
+
+/// Rungs that energise and de-energise the same coil under guards that can
+/// hold together; an error for critical coils, a warning otherwise.
+///
+/// Latching coils are set and reset by separate rungs by design, so they are
+/// not checked.
+fn conflicting_driver_warnings(module: &ast::Module, errors: &mut Vec<CompileError>) -> Vec<Diagnostic> {
+    // Each write is (rung, guard it happens under, whether it drives the coil high)
+    let mut writes: BTreeMap<&str, Vec<(&str, ast::GuardExpr, bool)>> = BTreeMap::new();
+    for rung in &module.rungs {
+        let else_guard = ast::GuardExpr::Not { expr: Box::new(rung.guard.clone()) };
+        let branches = [(&rung.actions, &rung.guard), (&rung.else_actions, &else_guard)];
+        for (actions, guard) in branches {
+            for action in actions.iter() {
+                let high = match action.action_type {
+                    ast::ActionType::Energise => !matches!(action.value, Some(ast::Expr::Boolean(false))),
+                    ast::ActionType::Set => true,
+                    ast::ActionType::DeEnergise | ast::ActionType::Reset => false,
+                    _ => continue,
+                };
+                writes.entry(&action.coil).or_default().push((&rung.name, guard.clone(), high));
+            }
+        }
+    }
+    
+    let mut warnings = Vec::new();
+    for coil in &module.coils {
+        if coil.latching == Some(true) {
+            continue;
+        }
+        let Some(writes) = writes.get(coil.name.as_str()) else {
+            continue;
+        };
+        let (mut on, mut off) = (BTreeSet::new(), BTreeSet::new());
+        for (rung, guard, _) in writes.iter().filter(|w| w.2) {
+            for (other, other_guard, _) in writes.iter().filter(|w| !w.2 && w.0 != *rung) {
+                let both = ast::GuardExpr::And { left: Box::new(guard.clone()), right: Box::new(other_guard.clone()) };
+                if !is_contradiction(&both) {
+                    on.insert(*rung);
+                    off.insert(*other);
+                }
+            }
+        }
+        if on.is_empty() {
+            continue;
+        }
+        let message = format!(
+            "coil '{}' is energised by rung {} and de-energised by rung {} under guards that can hold together",
+            coil.name,
+            on.into_iter().collect::<Vec<_>>().join(", "),
+            off.into_iter().collect::<Vec<_>>().join(", ")
+        );
+        if coil.critical == Some(true) {
+            errors.push(CompileError::name_resolution(message));
+        } else {
+            warnings.push(Diagnostic::warning(message));
+        }
+    }
+    warnings
+}
+
+/// Notes for NC contacts on coils that can never be open.
+///
+/// Deliberately conservative: a coil only counts as never open when some rung
+/// energises it under a guard that always holds and no rung ever drives it
+/// low. Input signals can always be open, so they are never flagged.
+fn stuck_nc_notes(module: &ast::Module, allowed: &[String]) -> Vec<Diagnostic> {
+    let drives_low = |action: &ast::Action| {
+        matches!(action.action_type, ast::ActionType::DeEnergise | ast::ActionType::Reset)
+            || (action.action_type == ast::ActionType::Energise && matches!(action.value, Some(ast::Expr::Boolean(false))))
+    };
+    let always_on = |coil: &str| {
+        let energised = module.rungs.iter().any(|rung| is_tautology(&rung.guard) && rung.actions.iter().any(|action| {
+            action.coil == coil && action.action_type == ast::ActionType::Energise && !drives_low(action)
+        }));
+        let cleared = module.rungs.iter()
+            .flat_map(|rung| rung.actions.iter().chain(&rung.else_actions))
+            .any(|action| action.coil == coil && drives_low(action));
+        energised && !cleared
+    };
+    
+    let mut notes = Vec::new();
+    for rung in &module.rungs {
+        let mut contacts = Vec::new();
+        collect_contacts_of_kind(&rung.guard, ast::ContactKind::Coil, &mut contacts);
+        for (name, contact_type) in contacts {
+            if contact_type == ast::ContactType::NC && !allowed.iter().any(|a| a == name) && always_on(name) {
+                notes.push(Diagnostic::note(format!(
+                    "NC contact on '{}' in rung {} never passes: '{}' is always energised (check NO/NC)",
+                    name, rung.name, name
+                )));
+            }
+        }
+    }
+    notes
+}
+
+fn collect_contact_names<'a>(guard: &'a ast::GuardExpr, names: &mut Vec<&'a str>) {
+    match guard {
+        ast::GuardExpr::Contact { name, .. } => names.push(name),
+        ast::GuardExpr::And { left, right } | ast::GuardExpr::Or { left, right } => {
+            collect_contact_names(left, names);
+            collect_contact_names(right, names);
+        }
+        ast::GuardExpr::Not { expr } => collect_contact_names(expr, names),
+        ast::GuardExpr::Compare { left, right, .. } => {
+            for side in [left, right] {
+                if let ast::Expr::Identifier(name) = side {
+                    names.push(name);
+                }
+            }
+        }
+    }
+}
+
+/// Signal pairs compared directly against each other, such as `supply > demand`
+fn collect_signal_comparisons<'a>(guard: &'a ast::GuardExpr, pairs: &mut Vec<(&'a str, &'a str)>) {
     match guard {
-        ast::GuardExpr::Contact { name, .. } => {
-            symbols.resolve_signal(name)?;
+        ast::GuardExpr::Compare { left: ast::Expr::Identifier(left), right: ast::Expr::Identifier(right), .. } => {
+            pairs.push((left, right));
+        }
+        ast::GuardExpr::Contact { .. } | ast::GuardExpr::Compare { .. } => {}
+        ast::GuardExpr::And { left, right } | ast::GuardExpr::Or { left, right } => {
+            collect_signal_comparisons(left, pairs);
+            collect_signal_comparisons(right, pairs);
+        }
+        ast::GuardExpr::Not { expr } => collect_signal_comparisons(expr, pairs),
+    }
+}
+
+fn resolve_guard(guard: &mut ast::GuardExpr, symbols: &SymbolTable, errors: &mut Vec<CompileError>) {
+    match guard {
+        ast::GuardExpr::Contact { name, kind, arguments, .. } => {
+            match symbols.resolve_contact(name) {
+                Ok(resolved) => *kind = Some(resolved),
+                Err(error) => errors.push(error),
+            }
+            record(symbols.check_contact_arguments(name, arguments.len()), errors);
         }
         ast::GuardExpr::And { left, right } => {
-            resolve_guard(left, symbols)?;
-            resolve_guard(right, symbols)?;
+            resolve_guard(left, symbols, errors);
+            resolve_guard(right, symbols, errors);
         }
         ast::GuardExpr::Or { left, right } => {
-            resolve_guard(left, symbols)?;
-            resolve_guard(right, symbols)?;
+            resolve_guard(left, symbols, errors);
+            resolve_guard(right, symbols, errors);
         }
         ast::GuardExpr::Not { expr } => {
-            resolve_guard(expr, symbols)?;
+            resolve_guard(expr, symbols, errors);
+        }
+        // Only signals carry values to compare
+        ast::GuardExpr::Compare { left, right, .. } => {
+            for side in [&*left, &*right] {
+                match side {
+                    ast::Expr::Identifier(name) if symbols.coils.contains_key(name) => errors.push(CompileError::Type(
+                        format!("Cannot compare coil '{}': only signals carry values", name)
+                    )),
+                    ast::Expr::Identifier(name) => record(symbols.resolve_signal(name), errors),
+                    _ => {}
+                }
+            }
         }
     }
-    Ok(())
 }
 
 #[cfg(test)]
@@ -117,6 +877,7 @@ mod tests {
     fn test_resolve_names() {
         let mut module = ast::Module {
             name: "test".to_string(),
+            is_library: false,
             context: None,
             intent: None,
             constraints: None,
@@ -124,12 +885,18 @@ mod tests {
                 name: "input".to_string(),
                 parameters: Vec::new(),
                 type_: None,
+                unit: None,
+                feature: None,
+                deprecated: None,
             }],
             coils: vec![ast::CoilDecl {
                 name: "output".to_string(),
                 parameters: Vec::new(),
                 latching: None,
                 critical: None,
+                unit: None,
+                feature: None,
+                deprecated: None,
             }],
             rungs: vec![ast::RungDecl {
                 name: "r1".to_string(),
@@ -137,17 +904,459 @@ mod tests {
                     name: "input".to_string(),
                     contact_type: ast::ContactType::NO,
                     arguments: Vec::new(),
+                    kind: None,
                 },
                 actions: vec![ast::Action {
                     action_type: ast::ActionType::Energise,
                     coil: "output".to_string(),
                     arguments: Vec::new(),
+                    value: None,
+                    condition: None,
                 }],
+                else_actions: Vec::new(),
+                feature: None,
             }],
             blocks: Vec::new(),
             networks: Vec::new(),
         };
         
+        assert!(resolve_names(&mut module).unwrap().is_empty());
+        match &module.rungs[0].guard {
+            ast::GuardExpr::Contact { kind, .. } => assert_eq!(*kind, Some(ast::ContactKind::Signal)),
+            other => panic!("expected a contact, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_critical_latch_requires_reset() {
+        let source = r#"
+module latch
+signal trip_request
+coil trip [latching, critical]
+rung set_trip:
+  when NO trip_request
+  then energise trip
+"#;
+        let mut module = crate::parse(source).unwrap();
+        let warnings = resolve_names(&mut module).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("critical coil 'trip' is latching"));
+        
+        let strict = ResolveOptions { strict: true, ..Default::default() };
+        let mut module = crate::parse(source).unwrap();
+        assert!(matches!(resolve_names_with(&mut module, &strict), Err(CompileError::Type(_))));
+        
+        let with_reset = format!("{}rung clear_trip:\n  when NC trip_request\n  then de_energise trip\n", source);
+        let mut module = crate::parse(&with_reset).unwrap();
+        assert!(resolve_names_with(&mut module, &strict).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_library_module_without_rungs_is_warning_free() {
+        let mut library = crate::parse("library module utils\nsignal shared_input").unwrap();
+        assert!(library.is_library);
+        assert!(resolve_names(&mut library).unwrap().is_empty());
+        
+        let mut module = crate::parse("module utils\nsignal shared_input").unwrap();
+        let warnings = resolve_names(&mut module).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("no rungs"));
+    }
+
+    #[test]
+    fn test_empty_block_warns() {
+        let source = "library module blocks\nblock stub:\nabstract block interface:";
+        let mut module = crate::parse(source).unwrap();
+        let warnings = resolve_names(&mut module).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("block 'stub' is empty"));
+        
+        module.blocks[0].inputs.push(ast::PortDecl {
+            name: "enable".to_string(),
+            type_: "bool".to_string(),
+        });
+        assert!(resolve_names(&mut module).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pure_block_rejects_escalate() {
+        let source = "module m\nsignal filtered\ncoil alarm\nblock smooth:\nrung r:\n  when NO filtered\n  then energise alarm";
+        let mut module = crate::parse(source).unwrap();
+        module.blocks[0].outputs.push(ast::PortDecl {
+            name: "filtered".to_string(),
+            type_: "bool".to_string(),
+        });
+        module.blocks[0].effect = Some("pure".to_string());
+        assert!(resolve_names(&mut module).is_ok());
+        
+        module.rungs[0].actions[0].action_type = ast::ActionType::Escalate;
+        match resolve_names(&mut module) {
+            Err(CompileError::Type(message)) => assert!(message.contains("pure block 'smooth'"), "{}", message),
+            other => panic!("expected a type error, got {:?}", other),
+        }
+        
+        module.blocks[0].effect = Some("stateful".to_string());
         assert!(resolve_names(&mut module).is_ok());
     }
+
+    #[test]
+    fn test_symbol_info() {
+        let module = crate::parse("module m\nsignal level\ncoil trip [critical, latching]\nblock filter:").unwrap();
+        let symbols = SymbolTable::from_module(&module).unwrap();
+        
+        let trip = symbols.symbol_info("trip").unwrap();
+        assert_eq!(trip.kind, SymbolKind::Coil);
+        assert!(trip.critical && trip.latching);
+        assert_eq!(symbols.symbol_info("level").unwrap().kind, SymbolKind::Signal);
+        assert_eq!(symbols.symbol_info("filter").unwrap().kind, SymbolKind::Block);
+        assert!(symbols.symbol_info("missing").is_none());
+    }
+
+    #[test]
+    fn test_duplicate_rung_block_and_network_names() {
+        let source = "module m\nsignal a\ncoil x\nrung safety:\n  when NO a\n  then energise x\nrung safety:\n  when NC a\n  then energise x\n";
+        let mut module = crate::parse(source).unwrap();
+        let err = resolve_names(&mut module).unwrap_err();
+        assert_eq!(err.to_string(), "Name resolution error: Duplicate rung name: safety");
+        
+        let mut module = crate::parse("library module m\nabstract block f:\nnetwork n:\nabstract block f:\nnetwork n:").unwrap();
+        let messages: Vec<String> = resolve_lenient(&mut module).into_iter().map(|d| d.message).collect();
+        assert_eq!(messages, ["Name resolution error: Duplicate block name: f", "Name resolution error: Duplicate network name: n"]);
+    }
+
+    #[test]
+    fn test_duplicate_wire_target() {
+        let mut module = crate::parse("library module m\nnetwork n:").unwrap();
+        let wire = |source: &str, target: &str, line: usize| ast::Wire {
+            source: source.to_string(),
+            target: target.to_string(),
+            position: Some(ast::Position { line, column: 3 }),
+        };
+        module.networks[0].wires = vec![wire("a", "t", 3), wire("c", "u", 4)];
+        assert!(resolve_names(&mut module).is_ok());
+        
+        module.networks[0].wires.push(wire("b", "t", 5));
+        match resolve_names(&mut module) {
+            Err(CompileError::NameResolution { message, .. }) => {
+                assert!(message.contains("'t'") && message.contains("'a'") && message.contains("'b'"), "{}", message);
+                assert!(message.ends_with("at line 5, column 3"), "{}", message);
+            }
+            other => panic!("expected a name resolution error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_duplicate_network_output() {
+        let mut module = crate::parse("library module m\nnetwork n:").unwrap();
+        let output = |line: usize| ast::Output {
+            name: "level".to_string(),
+            source: "filter.out".to_string(),
+            position: Some(ast::Position { line, column: 3 }),
+        };
+        module.networks[0].outputs = vec![output(3), output(6)];
+        let err = resolve_names(&mut module).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Name resolution error: Output 'level' in network 'n' is declared more than once at line 6, column 3"
+        );
+    }
+
+    #[test]
+    fn test_energise_arity() {
+        let check = |source: &str| {
+            let mut module = crate::parse(source).unwrap();
+            resolve_names(&mut module)
+        };
+        assert!(check("module m\nsignal a\ncoil c(level)\nrung r:\n  when NO a\n  then energise c(5)").is_ok());
+        
+        match check("module m\nsignal a\ncoil c\nrung r:\n  when NO a\n  then energise c(5)") {
+            Err(CompileError::Type(message)) => assert_eq!(message, "Too many arguments to coil 'c': expected 0, found 1"),
+            other => panic!("expected an arity error, got {:?}", other),
+        }
+        match check("module m\nsignal a\ncoil c(level, rate)\nrung r:\n  when NO a\n  then energise c(5)") {
+            Err(CompileError::Type(message)) => assert_eq!(message, "Too few arguments to coil 'c': expected 2, found 1"),
+            other => panic!("expected an arity error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_mixed_contact_lint() {
+        let source = "module m\nsignal a\ncoil x\ncoil y\nrung on:\n  when NO a\n  then energise x\nrung off:\n  when NC a\n  then energise y";
+        let mut module = crate::parse(source).unwrap();
+        assert!(resolve_names(&mut module).unwrap().is_empty());
+        
+        let options = ResolveOptions { mixed_contacts: true, ..Default::default() };
+        let warnings = resolve_names_with(&mut module, &options).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "signal 'a' is read as NO in on but as NC in off");
+    }
+
+    #[test]
+    fn test_block_composition() {
+        let mut module = crate::parse("library module m\nabstract block outer:\nabstract block inner:\nabstract block leaf:").unwrap();
+        module.blocks[0].implementation = Some("inner".to_string());
+        module.blocks[1].implementation = Some("leaf".to_string());
+        module.blocks[2].implementation = Some("vendor.filters.lowpass".to_string());
+        resolve_names(&mut module).unwrap();
+        assert_eq!(block_composition(&module.blocks, &module.blocks[0]).unwrap(), ["outer", "inner", "leaf"]);
+        
+        module.blocks[2].implementation = Some("outer".to_string());
+        match resolve_names(&mut module) {
+            Err(CompileError::NameResolution { message, .. }) => {
+                assert_eq!(message, "Cyclic block composition: outer -> inner -> leaf -> outer");
+            }
+            other => panic!("expected a cycle error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_require_condition_resolves_contacts() {
+        let source = "module m\nsignal start\nsignal door_closed\nsignal e_stop\ncoil motor\nrung r:\n  when NO start\n  then require (NO door_closed AND NO e_stop) energise motor";
+        let mut module = crate::parse(source).unwrap();
+        resolve_names(&mut module).unwrap();
+        match module.rungs[0].actions[0].condition.as_ref().unwrap() {
+            ast::GuardExpr::And { left, .. } => {
+                assert!(matches!(**left, ast::GuardExpr::Contact { kind: Some(ast::ContactKind::Signal), .. }));
+            }
+            other => panic!("unexpected condition {:?}", other),
+        }
+        
+        let mut module = crate::parse(&source.replace("signal e_stop\n", "")).unwrap();
+        assert!(matches!(resolve_names(&mut module), Err(CompileError::NameResolution { message: m, .. }) if m.contains("e_stop")));
+    }
+
+    #[test]
+    fn test_contact_arguments_list_parameters() {
+        let check = |contact: &str| {
+            let source = format!("module m\nsignal level(min, max)\nsignal start\ncoil x\nrung r:\n  when {}\n  then energise x", contact);
+            let mut module = crate::parse(&source).unwrap();
+            resolve_names(&mut module)
+        };
+        assert!(check("NO level(1, 5)").is_ok());
+        assert!(check("NO level(1)").is_ok());
+        match check("NO level(1, 5, 9)") {
+            Err(CompileError::NameResolution { message, .. }) => {
+                assert_eq!(message, "Contact 'level' passes 3 argument(s), but signal 'level' declares parameters (min, max)");
+            }
+            other => panic!("expected an argument error, got {:?}", other),
+        }
+        let err = check("NO start AND NC start(1)").unwrap_err();
+        assert_eq!(err.to_string(), "Name resolution error: Contact 'start' passes 1 argument(s), but signal 'start' declares no parameters");
+    }
+
+    #[test]
+    fn test_unit_mismatch() {
+        let source = "module m\nsignal inlet: real unit: \"degC\"\nsignal outlet: real unit: \"degC\"\nsignal pressure: real unit: \"bar\"";
+        let symbols = SymbolTable::from_module(&crate::parse(source).unwrap()).unwrap();
+        assert!(symbols.unit_mismatch("inlet", "outlet").is_none());
+        let warning = symbols.unit_mismatch("inlet", "pressure").unwrap();
+        assert_eq!(warning.message, "comparing 'inlet' (degC) with 'pressure' (bar) mixes units");
+    }
+
+    #[test]
+    fn test_strict_promotes_lints() {
+        let source = "module stubbed\nsignal input\n";
+        let mut module = crate::parse(source).unwrap();
+        assert_eq!(resolve_names(&mut module).unwrap().len(), 1);
+        
+        let strict = ResolveOptions { strict: true, ..Default::default() };
+        let mut module = crate::parse(source).unwrap();
+        match resolve_names_with(&mut module, &strict) {
+            Err(CompileError::Type(message)) => assert!(message.contains("has no rungs"), "{}", message),
+            other => panic!("expected a type error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_contradictory_require_warns() {
+        let source = "module m\nsignal a\ncoil x\nrung r:\n  when NO a\n  then require (NO a AND NOT NO a), energise x\n";
+        let mut module = crate::parse(source).unwrap();
+        let warnings = resolve_names(&mut module).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "rung 'r' can never complete its actions: `require NO a AND NOT NO a` can never hold");
+        
+        let mut module = crate::parse(&source.replace("NOT NO a", "NO a")).unwrap();
+        assert!(resolve_names(&mut module).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_lenient_reports_every_undefined_reference() {
+        let source = "module partial\nsignal start\ncoil motor\nrung r:\n  when NO start AND NO stop\n  then energise motr\n";
+        let mut module = crate::parse(source).unwrap();
+        let diagnostics = resolve_lenient(&mut module);
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Name resolution error: Undefined signal: stop",
+            "Name resolution error: Undefined coil: motr; did you mean `motor`?",
+        ]);
+        assert!(diagnostics.iter().all(|d| d.severity == crate::Severity::Error));
+        
+        // Contacts that do resolve are still annotated
+        let ast::GuardExpr::And { left, .. } = &module.rungs[0].guard else { panic!("expected AND") };
+        assert!(matches!(**left, ast::GuardExpr::Contact { kind: Some(ast::ContactKind::Signal), .. }));
+    }
+
+    #[test]
+    fn test_feature_gated_rung() {
+        let source = "module m\nsignal a\ncoil x\n#[if feature(\"debug\")]\ncoil trace\nrung r:\n  when NO a\n  then energise x\n#[if feature(\"debug\")]\nrung debug_trace:\n  when NO a\n  then energise trace\n";
+        let mut module = crate::parse(source).unwrap();
+        resolve_names(&mut module).unwrap();
+        let rungs: Vec<&str> = module.rungs.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(rungs, ["r"]);
+        assert_eq!(module.coils.len(), 1);
+        
+        let debug = ResolveOptions { features: vec!["debug".to_string()], ..Default::default() };
+        let mut module = crate::parse(source).unwrap();
+        resolve_names_with(&mut module, &debug).unwrap();
+        let rungs: Vec<&str> = module.rungs.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(rungs, ["r", "debug_trace"]);
+    }
+
+    #[test]
+    fn test_nc_on_always_energised_coil() {
+        let source = "module m\nsignal a\ncoil ready\ncoil run\nrung boot:\n  when NO a OR NC a\n  then energise ready\nrung go:\n  when NC ready AND NC a\n  then energise run\n";
+        let mut module = crate::parse(source).unwrap();
+        let notes = resolve_names(&mut module).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].severity, Severity::Note);
+        assert_eq!(notes[0].message, "NC contact on 'ready' in rung go never passes: 'ready' is always energised (check NO/NC)");
+        
+        let allowed = ResolveOptions { allow_nc: vec!["ready".to_string()], ..Default::default() };
+        let mut module = crate::parse(source).unwrap();
+        assert!(resolve_names_with(&mut module, &allowed).unwrap().is_empty());
+        
+        // Once some rung can clear the coil, reading it through NC is legitimate
+        let cleared = format!("{}rung clear:\n  when NO a\n  then de_energise ready\n", source);
+        let mut module = crate::parse(&cleared).unwrap();
+        let warnings = resolve_names(&mut module).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.starts_with("coil 'ready' is energised by rung boot"), "{}", warnings[0].message);
+    }
+
+    #[test]
+    fn test_conflicting_drivers() {
+        let source = "module m\nsignal start\nsignal stop\ncoil pump\nrung run:\n  when NO start\n  then energise pump\nrung halt:\n  when NO stop\n  then de_energise pump\n";
+        let mut module = crate::parse(source).unwrap();
+        let warnings = resolve_names(&mut module).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "coil 'pump' is energised by rung run and de-energised by rung halt under guards that can hold together");
+        
+        // Guards that exclude each other never fight over the coil
+        let mut module = crate::parse(&source.replace("when NO stop", "when NC start")).unwrap();
+        assert!(resolve_names(&mut module).unwrap().iter().all(|w| !w.message.contains("de-energised")));
+        
+        let mut module = crate::parse(&source.replace("coil pump", "coil pump [critical]")).unwrap();
+        let err = resolve_names(&mut module).unwrap_err();
+        assert!(matches!(err, CompileError::NameResolution { .. }), "{:?}", err);
+        
+        let mut module = crate::parse(&source.replace("coil pump", "coil pump [latching]")).unwrap();
+        assert!(resolve_names(&mut module).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_undefined_signal_suggests_closest_name() {
+        let mut module = crate::parse("module m\nsignal input\nsignal inputs_ok\ncoil out\nrung r:\n  when NO inpt\n  then energise out\n").unwrap();
+        let diagnostics = resolve_lenient(&mut module);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].suggestion.as_deref(), Some("input"));
+        
+        let names = ["alpha".to_string(), "omega".to_string()];
+        assert_eq!(closest_name("zzz", names.iter()), None);
+    }
+
+    #[test]
+    fn test_undefined_coil_did_you_mean() {
+        let mut module = crate::parse("module m\nsignal ouput_ready\ncoil output\nrung r:\n  when NO ouput_ready\n  then energise ouput\n").unwrap();
+        let err = resolve_names(&mut module).unwrap_err();
+        // Actions drive coils, so the similarly named signal is not offered
+        assert_eq!(err.to_string(), "Name resolution error: Undefined coil: ouput; did you mean `output`?");
+        
+        let mut module = crate::parse("module m\nsignal output_ok\ncoil x\nrung r:\n  when NO unrelated\n  then energise x\n").unwrap();
+        let err = resolve_names(&mut module).unwrap_err();
+        assert_eq!(err.to_string(), "Name resolution error: Undefined signal: unrelated");
+    }
+
+    #[test]
+    fn test_rung_without_actions_warns() {
+        let mut module = crate::parse("module m\nsignal a\nrung r:\n  when NO a\n  then\n").unwrap();
+        let warnings = resolve_names(&mut module).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "rung 'r' has no actions after `then`");
+    }
+
+    #[test]
+    fn test_conditional_driver_of_unconditional_coil_warns() {
+        let source = "module m\nsignal a\nsignal b\ncoil c\nrung always:\n  when NO a OR NC a\n  then energise c\nrung when_b:\n  when NO b\n  then energise c\n";
+        let mut module = crate::parse(source).unwrap();
+        let warnings = resolve_names(&mut module).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "coil 'c' is energised unconditionally by rung always, so energising it in when_b has no effect");
+        
+        let mut module = crate::parse(&source.replace("NO a OR NC a", "NO a")).unwrap();
+        assert!(resolve_names(&mut module).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_deprecated_signal_reference_warns() {
+        let source = "module m\n@deprecated(\"use level_ok instead\")\nsignal level_high\nsignal level_ok\ncoil pump\nrung r:\n  when NO level_high AND NC level_high AND NO level_ok\n  then energise pump\n";
+        let mut module = crate::parse(source).unwrap();
+        let warnings = resolve_names(&mut module).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "rung 'r' uses deprecated signal 'level_high': use level_ok instead");
+        
+        let mut module = crate::parse(&source.replace("NO level_high AND NC level_high", "NO level_ok")).unwrap();
+        let warnings = resolve_names(&mut module).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "unused signal 'level_high'");
+    }
+
+    #[test]
+    fn test_unused_signals_and_coils_warn() {
+        let source = "module m\nsignal start\nsignal spare_input\ncoil motor\ncoil spare_output\nrung r:\n  when NO start\n  then energise motor\n";
+        let mut module = crate::parse(source).unwrap();
+        let messages: Vec<String> = resolve_names(&mut module).unwrap().into_iter().map(|w| w.message).collect();
+        assert_eq!(messages, ["unused signal 'spare_input'", "unused coil 'spare_output'"]);
+        
+        let mut library = crate::parse(&source.replace("module m", "library module m")).unwrap();
+        assert!(resolve_names(&mut library).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_require_target_may_be_signal() {
+        let source = "module m\nsignal start\nsignal operator_ack\ncoil motor\nrung r:\n  when NO start\n  then require operator_ack, energise motor\n";
+        let mut module = crate::parse(source).unwrap();
+        assert!(resolve_names(&mut module).unwrap().is_empty());
+        
+        let mut module = crate::parse(&source.replace("require operator_ack", "require operator_akc")).unwrap();
+        let err = resolve_names(&mut module).unwrap_err();
+        assert_eq!(err.to_string(), "Name resolution error: Undefined signal or coil in require: operator_akc; did you mean `operator_ack`?");
+    }
+
+    #[test]
+    fn test_set_and_reset_need_latching_coils() {
+        let source = "module m\nsignal trip\nsignal ack\ncoil alarm [latching, critical]\nrung raise:\n  when NO trip\n  then set alarm\nrung clear:\n  when NO ack\n  then reset alarm\n";
+        let mut module = crate::parse(source).unwrap();
+        assert!(resolve_names(&mut module).unwrap().is_empty());
+        
+        let mut module = crate::parse(&source.replace(" [latching, critical]", "")).unwrap();
+        let err = resolve_names(&mut module).unwrap_err();
+        assert_eq!(err.to_string(), "Type error: `set` targets coil 'alarm', which is not latching (declare it as `coil alarm [latching]`)");
+    }
+
+    #[test]
+    fn test_comparison_guards_resolve_signals() {
+        let source = "module m\nsignal inlet unit: \"degC\"\nsignal outlet unit: \"degF\"\ncoil fan\nrung r:\n  when inlet > outlet\n  then energise fan\n";
+        let mut module = crate::parse(source).unwrap();
+        let warnings = resolve_names(&mut module).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "comparing 'inlet' (degC) with 'outlet' (degF) mixes units");
+        
+        let mut module = crate::parse(&source.replace("when inlet >", "when inlets >")).unwrap();
+        let err = resolve_names(&mut module).unwrap_err();
+        assert_eq!(err.to_string(), "Name resolution error: Undefined signal: inlets; did you mean `inlet`?");
+        
+        let mut module = crate::parse(&source.replace("when inlet >", "when fan >")).unwrap();
+        let err = resolve_names(&mut module).unwrap_err();
+        assert_eq!(err.to_string(), "Type error: Cannot compare coil 'fan': only signals carry values");
+    }
 }
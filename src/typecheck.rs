@@ -0,0 +1,168 @@
+use crate::ast::{Expr, GuardExpr, Module};
+use crate::error::{CompileError, Result};
+use std::collections::HashMap;
+
+/// Whether a declared signal type holds a number
+pub fn is_numeric_type(type_: &str) -> bool {
+    matches!(type_, "int" | "real" | "float" | "number" | "analog")
+}
+
+/// Whether a declared signal type holds a boolean
+pub fn is_boolean_type(type_: &str) -> bool {
+    matches!(type_, "bool" | "boolean")
+}
+
+/// Check the semantic types of a resolved module.
+///
+/// Comparisons must read numeric signals and literals, and edge contacts
+/// boolean ones; signals with no declared type are not checked. Explicit `energise` values must be
+/// boolean. Quality constraints must be fractions.
+/// Signal and coil parameters are untyped, so arguments are not checked.
+pub fn check_types(module: &Module) -> Result<()> {
+    // Coils always hold a boolean
+    let types: HashMap<&str, Option<&str>> = module.coils.iter()
+        .map(|c| (c.name.as_str(), Some("bool")))
+        .chain(module.signals.iter().map(|s| (s.name.as_str(), s.type_.as_deref())))
+        .collect();
+    
+    for rung in &module.rungs {
+        check_guard(&rung.guard, &types)?;
+        for action in rung.actions.iter().chain(&rung.else_actions) {
+            if let Some(condition) = &action.condition {
+                check_guard(condition, &types)?;
+            }
+            if let Some(value) = &action.value {
+                check_value(&action.coil, value, &types)?;
+            }
+        }
+    }
+    
+    if let Some(quality) = module.constraints.as_ref().and_then(|c| c.quality.as_ref()) {
+        for (key, value) in [("min_precision", quality.min_precision), ("min_recall", quality.min_recall)] {
+            if let Some(value) = value.filter(|v| !(0.0..=1.0).contains(v)) {
+                return Err(CompileError::Type(format!(
+                    "Quality constraint `{}` must be between 0 and 1, found {}", key, value
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_value(coil: &str, value: &Expr, types: &HashMap<&str, Option<&str>>) -> Result<()> {
+    let boolean = match value {
+        Expr::Boolean(_) => true,
+        Expr::Identifier(name) => types.get(name.as_str()).copied().flatten().is_none_or(is_boolean_type),
+        Expr::Number(_) | Expr::String(_) => false,
+    };
+    if boolean {
+        Ok(())
+    } else {
+        Err(CompileError::Type(format!(
+            "Value of `energise {} = {}` must be boolean", coil, value
+        )))
+    }
+}
+
+fn check_guard(guard: &GuardExpr, types: &HashMap<&str, Option<&str>>) -> Result<()> {
+    match guard {
+        GuardExpr::Contact { name, contact_type, .. } => match types.get(name.as_str()) {
+            // Edges detect a boolean changing state
+            Some(Some(type_)) if contact_type.is_edge() && !is_boolean_type(type_) => Err(CompileError::Type(format!(
+                "Edge contact `{}` needs a boolean signal, but '{}' is declared as {}", guard, name, type_
+            ))),
+            _ => Ok(()),
+        },
+        GuardExpr::And { left, right } | GuardExpr::Or { left, right } => {
+            check_guard(left, types)?;
+            check_guard(right, types)
+        }
+        GuardExpr::Not { expr } => check_guard(expr, types),
+        GuardExpr::Compare { left, right, .. } => {
+            for side in [left, right] {
+                match side {
+                    Expr::Number(_) => {}
+                    Expr::Identifier(name) => match types.get(name.as_str()) {
+                        Some(Some(type_)) if !is_numeric_type(type_) => {
+                            return Err(CompileError::Type(format!(
+                                "Comparison `{}` needs a numeric signal, but '{}' is declared as {}", guard, name, type_
+                            )));
+                        }
+                        _ => {}
+                    },
+                    Expr::String(_) | Expr::Boolean(_) => {
+                        return Err(CompileError::Type(format!(
+                            "Comparison `{}` needs numeric operands, but `{}` is not a number", guard, side
+                        )));
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Result<()> {
+        let mut module = crate::parse(source).unwrap();
+        crate::resolve_names(&mut module).unwrap();
+        check_types(&module)
+    }
+
+    #[test]
+    fn test_comparisons_need_numeric_operands() {
+        let source = "module m\nsignal level: real\nsignal ready: bool\nsignal raw\ncoil pump\nrung r:\n  when level > 80 AND raw < 3 AND NO ready\n  then energise pump\n";
+        assert!(check(source).is_ok());
+        
+        match check(&source.replace("raw < 3", "ready < 3")) {
+            Err(CompileError::Type(message)) => {
+                assert_eq!(message, "Comparison `ready < 3` needs a numeric signal, but 'ready' is declared as bool");
+            }
+            other => panic!("expected a type error, got {:?}", other),
+        }
+        match check(&source.replace("level > 80", "level > true")) {
+            Err(CompileError::Type(message)) => {
+                assert_eq!(message, "Comparison `level > true` needs numeric operands, but `true` is not a number");
+            }
+            other => panic!("expected a type error, got {:?}", other),
+        }
+        
+        // The resolver rejects coil operands first; the type check holds on its own too
+        let module = crate::parse(&source.replace("raw < 3", "level > pump")).unwrap();
+        let err = check_types(&module).unwrap_err();
+        assert_eq!(err.to_string(), "Type error: Comparison `level > pump` needs a numeric signal, but 'pump' is declared as bool");
+    }
+
+    #[test]
+    fn test_energise_values_are_boolean() {
+        let source = "module m\nsignal ready: bool\nsignal level: real\ncoil pump\nrung r:\n  when NO ready\n  then energise pump = ready\n";
+        assert!(check(source).is_ok());
+        assert!(check(&source.replace("= ready", "= false")).is_ok());
+        
+        let err = check(&source.replace("= ready", "= 0x1F")).unwrap_err();
+        assert_eq!(err.to_string(), "Type error: Value of `energise pump = 31` must be boolean");
+        assert!(check(&source.replace("= ready", "= level")).is_err());
+    }
+
+    #[test]
+    fn test_edge_contacts_need_boolean_signals() {
+        let source = "module m\nsignal start: bool\nsignal level: real\ncoil run\nrung r:\n  when P start AND N run\n  then energise run\n";
+        assert!(check(source).is_ok());
+        
+        let err = check(&source.replace("P start", "P level")).unwrap_err();
+        assert_eq!(err.to_string(), "Type error: Edge contact `P level` needs a boolean signal, but 'level' is declared as real");
+    }
+
+    #[test]
+    fn test_quality_constraints_are_fractions() {
+        let mut module = crate::parse("library module m\nconstraints:\n  quality:\n    min_precision: 0.9").unwrap();
+        assert!(check_types(&module).is_ok());
+        
+        module.constraints.as_mut().unwrap().quality.as_mut().unwrap().min_precision = Some(1.5);
+        let err = check_types(&module).unwrap_err();
+        assert_eq!(err.to_string(), "Type error: Quality constraint `min_precision` must be between 0 and 1, found 1.5");
+    }
+}
@@ -0,0 +1,195 @@
+use crate::ast::ActionType;
+use crate::error::{CompileError, Result};
+use charta_core::ir::schema::{GuardExpr, IR};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// An IR action type, preserving names this compiler does not know.
+///
+/// IR produced by a newer compiler may use actions (e.g. `force`) that this
+/// version has no `ActionType` for; those load as `Unknown` instead of failing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IrActionType {
+    Known(ActionType),
+    Unknown(String),
+}
+
+impl IrActionType {
+    pub fn parse(name: &str) -> Self {
+        match ActionType::from_ir_name(name) {
+            Some(action_type) => IrActionType::Known(action_type),
+            None => IrActionType::Unknown(name.to_string()),
+        }
+    }
+}
+
+/// Whether `name` matches the identifier grammar `[a-zA-Z_][a-zA-Z0-9_]*`
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Check that every reference in an IR program resolves to a declaration.
+///
+/// This works on the IR alone, so it also catches hand-written or
+/// post-processed IR that never went through the compiler's resolver.
+pub fn validate_ir_semantics(ir: &IR) -> Result<()> {
+    let module = &ir.module;
+    if !is_identifier(&module.name) {
+        return Err(CompileError::name_resolution(
+            format!("IR module name '{}' is not a valid identifier", module.name)
+        ));
+    }
+    let signals: HashSet<&str> = module.signals.iter().flatten().map(|s| s.name.as_str()).collect();
+    let coils: HashSet<&str> = module.coils.iter().flatten().map(|c| c.name.as_str()).collect();
+    
+    for rung in module.rungs.iter().flatten() {
+        let mut contacts = Vec::new();
+        collect_contacts(&rung.guard, &mut contacts);
+        for name in contacts {
+            if !signals.contains(name) && !coils.contains(name) {
+                return Err(CompileError::name_resolution(
+                    format!("IR rung '{}' reads undeclared signal or coil: {}", rung.name, name)
+                ));
+            }
+        }
+        
+        for action in &rung.actions {
+            // Unknown actions come from newer IR; their target semantics are not ours to check
+            match IrActionType::parse(&action.action_type) {
+                IrActionType::Unknown(_) => continue,
+                // Conditional requires assert a guard instead of driving a coil
+                IrActionType::Known(ActionType::Require) if action.coil.is_empty() => continue,
+                // Other requires name a precondition signal or coil
+                IrActionType::Known(ActionType::Require) => {
+                    if !signals.contains(action.coil.as_str()) && !coils.contains(action.coil.as_str()) {
+                        return Err(CompileError::name_resolution(
+                            format!("IR rung '{}' requires undeclared signal or coil: {}", rung.name, action.coil)
+                        ));
+                    }
+                    continue;
+                }
+                IrActionType::Known(_) => {}
+            }
+            if !coils.contains(action.coil.as_str()) {
+                return Err(CompileError::name_resolution(
+                    format!("IR rung '{}' drives undeclared coil: {}", rung.name, action.coil)
+                ));
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Map each IR coil to the signals read by the guards of rungs that drive it,
+/// mirroring `resolver::coil_dependencies` for programs available only as IR
+pub fn ir_coil_dependencies(ir: &IR) -> BTreeMap<String, Vec<String>> {
+    let module = &ir.module;
+    let signals: HashSet<&str> = module.signals.iter().flatten().map(|s| s.name.as_str()).collect();
+    let mut dependencies: BTreeMap<String, BTreeSet<String>> = module.coils.iter().flatten()
+        .map(|c| (c.name.clone(), BTreeSet::new()))
+        .collect();
+    
+    for rung in module.rungs.iter().flatten() {
+        let mut contacts = Vec::new();
+        collect_contacts(&rung.guard, &mut contacts);
+        for action in rung.actions.iter().filter(|a| a.action_type != "require") {
+            if let Some(deps) = dependencies.get_mut(&action.coil) {
+                deps.extend(contacts.iter().filter(|n| signals.contains(*n)).map(|n| n.to_string()));
+            }
+        }
+    }
+    
+    dependencies.into_iter()
+        .map(|(coil, deps)| (coil, deps.into_iter().collect()))
+        .collect()
+}
+
+fn collect_contacts<'a>(guard: &'a GuardExpr, names: &mut Vec<&'a str>) {
+    match guard {
+        GuardExpr::Contact { name, .. } => names.push(name),
+        GuardExpr::And { left, right } | GuardExpr::Or { left, right } => {
+            collect_contacts(left, names);
+            collect_contacts(right, names);
+        }
+        GuardExpr::Not { expr } => collect_contacts(expr, names),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use charta_core::ir::schema::{Action, CoilDecl, Module, RungDecl, SignalDecl};
+
+    fn ir_with_action_coil(coil: &str) -> IR {
+        IR {
+            version: "0.1.0".to_string(),
+            module: Module {
+                name: "handmade".to_string(),
+                context: None,
+                intent: None,
+                constraints: None,
+                signals: Some(vec![SignalDecl {
+                    name: "input".to_string(),
+                    parameters: None,
+                    type_: None,
+                }]),
+                coils: Some(vec![CoilDecl {
+                    name: "output".to_string(),
+                    parameters: None,
+                    latching: None,
+                    critical: None,
+                }]),
+                rungs: Some(vec![RungDecl {
+                    name: "r1".to_string(),
+                    guard: GuardExpr::Contact {
+                        name: "input".to_string(),
+                        contact_type: "NO".to_string(),
+                        arguments: None,
+                    },
+                    actions: vec![Action {
+                        action_type: "energise".to_string(),
+                        coil: coil.to_string(),
+                        arguments: None,
+                    }],
+                }]),
+                blocks: None,
+                networks: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_validate_ir_semantics() {
+        assert!(validate_ir_semantics(&ir_with_action_coil("output")).is_ok());
+        
+        // Well-formed JSON shape, but the action targets a coil that is not declared
+        let err = validate_ir_semantics(&ir_with_action_coil("missing")).unwrap_err();
+        assert!(err.to_string().contains("undeclared coil: missing"));
+    }
+
+    #[test]
+    fn test_module_name_must_be_identifier() {
+        assert!(is_identifier("pump_2"));
+        assert!(!is_identifier("2pump"));
+        assert!(!is_identifier(""));
+        
+        let mut ir = ir_with_action_coil("output");
+        ir.module.name = "plant/pumps".to_string();
+        let err = validate_ir_semantics(&ir).unwrap_err();
+        assert!(err.to_string().contains("'plant/pumps' is not a valid identifier"));
+    }
+
+    #[test]
+    fn test_unknown_action_type_loads() {
+        let mut ir_json = serde_json::to_value(ir_with_action_coil("output")).unwrap();
+        ir_json["module"]["rungs"][0]["actions"][0]["action_type"] = "force".into();
+        
+        let ir: IR = serde_json::from_value(ir_json).unwrap();
+        let action_type = &ir.module.rungs.as_ref().unwrap()[0].actions[0].action_type;
+        assert_eq!(IrActionType::parse(action_type), IrActionType::Unknown("force".to_string()));
+        assert_eq!(IrActionType::parse("energise"), IrActionType::Known(ActionType::Energise));
+        assert!(validate_ir_semantics(&ir).is_ok());
+    }
+}